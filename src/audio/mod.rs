@@ -1,11 +1,15 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use ringbuf::HeapProd;
 use ringbuf::{traits::*, HeapRb};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::{mem, sync::OnceLock};
 
 pub static ENGINE: OnceLock<Engine> = OnceLock::new();
 
+/// Seconds of queued audio the engine holds, longer playbacks are cut off.
+pub const BUFFER_SECONDS: usize = 30;
+
 pub fn get() -> Option<&'static Engine> {
     match ENGINE.get() {
         Some(engine) => Some(engine),
@@ -25,6 +29,8 @@ pub struct Engine {
     // producer: Caching<Arc<SharedRb<f32>>, true, false>,
     // producer: dyn Producer<Item = f32>,
     producer: Mutex<HeapProd<f32>>,
+    /// tells the output callback to drop the queued samples
+    stop: Arc<AtomicBool>,
 }
 
 impl Engine {
@@ -36,13 +42,19 @@ impl Engine {
 
         // let (sender, receiver) = mpsc::channel();
         // let data = Arc::new(Mutex::new(Vec::new()));
-        let rb = HeapRb::<f32>::new(48_000);
+        let channels = supported_config.channels() as usize;
+        let rb = HeapRb::<f32>::new(48_000 * BUFFER_SECONDS * channels);
         let (prod, mut cons) = rb.split();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_callback = stop.clone();
 
         let stream = device
             .build_output_stream(
                 &supported_config.clone().into(),
                 move |d: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    if stop_callback.swap(false, Ordering::Relaxed) {
+                        cons.clear();
+                    }
                     let count = cons.pop_slice(d);
                     if count < d.len() {
                         // d[count..] = [0.0; d.len() - count];
@@ -83,6 +95,7 @@ impl Engine {
         Some(Engine {
             config: supported_config.into(),
             producer: Mutex::new(prod),
+            stop,
         })
     }
 
@@ -95,6 +108,20 @@ impl Engine {
         // d.append(&mut data);
     }
 
+    /// Whether queued samples are still being played.
+    pub fn is_playing(&self) -> bool {
+        !self.producer.lock().unwrap().is_empty()
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.config.sample_rate.0 as usize
+    }
+
+    /// Drops the samples that have not been played yet.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
     fn interleave(&self, input: &[f32]) -> Vec<f32> {
         let mut buffer = Vec::new();
         let n = self.config.channels;
@@ -0,0 +1,180 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::format;
+use crate::modules::{check_expectations, render, write_atomic, Profiling, TEST_RATE};
+
+/// Longest profile `mksnd profile` renders, an hour.
+const PROFILE_LIMIT: f64 = 3600.0;
+
+/// Runs `mksnd <command> ...` without opening a window, `None` if the first argument
+/// is not a command.
+pub fn run(args: &[OsString]) -> Option<Result<(), String>> {
+    let (command, args) = args.split_first()?;
+    let args = args
+        .iter()
+        .map(|a| a.to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    match command.to_str()? {
+        "fmt" => Some(fmt(&args)),
+        "profile" => Some(profile(&args)),
+        "render" => Some(render(&args)),
+        "test" => Some(test(&args)),
+        _ => None,
+    }
+}
+
+/// Module files in `path`, which is a module or a folder of them.
+fn module_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let dir = fs::read_dir(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    for entry in dir.flatten() {
+        // skips the temp files of atomic saves and the project data
+        if !entry.file_name().to_string_lossy().starts_with('.') {
+            module_files(&entry.path(), files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Module files in `paths`, by default the modules of the project in the current folder.
+fn collect_modules(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        module_files(path, &mut files)?;
+    }
+    if paths.is_empty() {
+        module_files(Path::new("modules"), &mut files)?;
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// `mksnd fmt [--check] [paths]` formats the modules in place, by default the modules
+/// of the project in the current folder. With `--check` nothing is written and it fails
+/// if a module is not formatted.
+fn fmt(args: &[String]) -> Result<(), String> {
+    let check = args.iter().any(|a| a == "--check");
+    let paths = args
+        .iter()
+        .filter(|a| *a != "--check")
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let files = collect_modules(&paths)?;
+
+    let mut failed = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(&file).map_err(|e| format!("{}: {e}", file.display()))?;
+        let formatted = match format::format(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                failed.push(format!("{}: {e}", file.display()));
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            failed.push(format!("{}: not formatted", file.display()));
+        } else {
+            write_atomic(&file, &formatted).map_err(|e| format!("{}: {e}", file.display()))?;
+            println!("formatted {}", file.display());
+        }
+    }
+
+    match failed.is_empty() {
+        true => Ok(()),
+        false => Err(failed.join("\n")),
+    }
+}
+
+/// `mksnd profile <module> [seconds]` renders the module and prints how fast it runs
+/// compared to real time at 48 kHz.
+fn profile(args: &[String]) -> Result<(), String> {
+    let Some(file) = args.first() else {
+        return Err("usage: mksnd profile <module> [seconds]".into());
+    };
+    let seconds = match args.get(1) {
+        Some(seconds) => seconds
+            .parse::<f64>()
+            .map_err(|e| format!("seconds {seconds:?}: {e}"))?,
+        None => 1.0,
+    };
+    // also false for NaN
+    if !(seconds > 0.0 && seconds <= PROFILE_LIMIT) {
+        return Err(format!(
+            "usage: mksnd profile <module> [seconds], seconds between 0 and {PROFILE_LIMIT}"
+        ));
+    }
+
+    let source = fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?;
+    let profile = Profiling::start(&source, seconds, TEST_RATE)
+        .and_then(Profiling::wait)
+        .map_err(|d| format!("{file}: {}", d.message))?;
+    println!("{}", profile.report());
+    match profile.realtime_factor() >= 1.0 {
+        true => Ok(()),
+        false => Err(format!("{file} is too slow to play in real time")),
+    }
+}
+
+/// `mksnd render <function> <start> <end> <scale>` renders the module read from stdin
+/// for [`render::Render`], which runs it as a child process so it can be killed. The
+/// result is written to stdout, errors to stderr, see [`render::encode`].
+fn render(args: &[String]) -> Result<(), String> {
+    let [function, start, end, scale] = args else {
+        return Err("usage: mksnd render <function> <start> <end> <scale>".into());
+    };
+    let number = |arg: &String| arg.parse::<usize>().map_err(|e| format!("{arg:?}: {e}"));
+    let (start, end) = (number(start)?, number(end)?);
+    let scale = scale
+        .parse::<f64>()
+        .map_err(|e| format!("{scale:?}: {e}"))?;
+
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| format!("stdin: {e}"))?;
+    let rendered =
+        render::run(&source, function, start..end, scale).map_err(|d| render::encode_error(&d))?;
+    io::stdout()
+        .write_all(&render::encode(&rendered))
+        .map_err(|e| format!("stdout: {e}"))
+}
+
+/// `mksnd test [paths]` compiles and renders every module like TEST and checks its
+/// `@expect` lines, without touching the audio device. Fails if any module does.
+fn test(args: &[String]) -> Result<(), String> {
+    let paths = args.iter().map(PathBuf::from).collect::<Vec<_>>();
+    let files = collect_modules(&paths)?;
+
+    let mut failed = 0;
+    for file in &files {
+        let source = fs::read_to_string(file).map_err(|e| format!("{}: {e}", file.display()))?;
+        let failures = match check_expectations(&source) {
+            Ok(failures) => failures,
+            Err(diagnostic) => vec![diagnostic.summary()],
+        };
+        if failures.is_empty() {
+            println!("ok   {}", file.display());
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {}", file.display());
+        for failure in failures {
+            println!("     {failure}");
+        }
+    }
+
+    println!("{} passed, {failed} failed", files.len() - failed);
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} modules failed")),
+    }
+}
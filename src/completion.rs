@@ -0,0 +1,267 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use bs::parser::Node;
+
+use crate::tokens;
+
+/// Names that may be builtins of the executor, those it doesn't run are left out.
+const BUILTINS: &[&str] = &[
+    "sin", "cos", "tan", "asin", "acos", "atan", "abs", "sqrt", "exp", "ln", "log", "floor",
+    "ceil", "round", "fract", "sign",
+];
+
+/// Words offered as keywords, those the lexer doesn't read as one are left out.
+const KEYWORDS: &[&str] = &[
+    "fn", "let", "return", "if", "else", "while", "for", "loop", "break", "true", "false",
+];
+
+/// Suggestions shown at once.
+pub const COMPLETION_LIMIT: usize = 8;
+
+/// Name, parameters and return type of a function, shown as `fn main(input: Num) -> Num`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    /// name and type of each parameter
+    pub params: Vec<(String, String)>,
+    pub ret: Option<String>,
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|(name, kind)| format!("{name}: {kind}"))
+            .collect::<Vec<_>>();
+        write!(f, "fn {}({})", self.name, params.join(", "))?;
+        match &self.ret {
+            Some(ret) => write!(f, " -> {ret}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ItemKind {
+    Function,
+    Builtin,
+    Keyword,
+}
+
+/// A word that can complete the one under the cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    pub label: String,
+    /// signature of functions, the kind of anything else
+    pub detail: String,
+    pub kind: ItemKind,
+}
+
+fn keywords() -> &'static [&'static str] {
+    static KNOWN: OnceLock<Vec<&str>> = OnceLock::new();
+    KNOWN.get_or_init(|| {
+        KEYWORDS
+            .iter()
+            .copied()
+            .filter(|k| tokens::is_keyword(k))
+            .collect()
+    })
+}
+
+/// Whether the executor runs `name` as a function taking and returning one `Num`,
+/// found by building and calling a module that only calls it.
+fn is_builtin(name: &str) -> bool {
+    let probe = format!("fn main(input: Num) -> Num {{\n  return {name}(input);\n}}\n");
+    let Ok(ast) = bs::parser::parse(bs::lexer::tokenize(&probe)) else {
+        return false;
+    };
+    let Ok(executor) = bs::executor::Executor::build(bs::parser::Ast::new(ast)) else {
+        return false;
+    };
+    matches!(
+        executor.execute("main", vec![&0.5]),
+        Ok(Some(bs::data::Value::Data(bs::data::DataType::Float(_))))
+    )
+}
+
+fn builtins() -> impl Iterator<Item = Signature> {
+    static KNOWN: OnceLock<Vec<&str>> = OnceLock::new();
+    let known = KNOWN.get_or_init(|| BUILTINS.iter().copied().filter(|b| is_builtin(b)).collect());
+    known.iter().map(|name| Signature {
+        name: name.to_string(),
+        params: vec![("x".into(), "Num".into())],
+        ret: Some("Num".into()),
+    })
+}
+
+/// Functions defined in `source`, read from its syntax tree, empty if it doesn't parse.
+pub fn signatures(source: &str) -> Vec<Signature> {
+    let Ok(nodes) = bs::parser::parse(bs::lexer::tokenize(source)) else {
+        return Vec::new();
+    };
+    nodes
+        .into_iter()
+        .filter_map(|node| match node {
+            Node::Function {
+                name,
+                args,
+                returns,
+                ..
+            } => Some(Signature {
+                name,
+                params: args,
+                ret: returns,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The part of the word left of the byte `column` of `line`.
+pub fn prefix(line: &str, column: usize) -> &str {
+    let before = line.get(..column).unwrap_or(line);
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map_or(before.len(), |(i, _)| i);
+    &before[start..]
+}
+
+/// Name of the function whose argument list the byte `column` of `line` is in.
+pub fn call(line: &str, column: usize) -> Option<&str> {
+    let before = line.get(..column).unwrap_or(line);
+    let mut depth = 0;
+    for (i, c) in before.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' if depth > 0 => depth -= 1,
+            '(' => {
+                let name = before[..i].trim_end();
+                let name = prefix(name, name.len());
+                // `return (a + b)` is no call
+                return (!name.is_empty() && !tokens::is_keyword(name)).then_some(name);
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Signature of a function defined in the module or a builtin.
+pub fn lookup(name: &str, functions: &[Signature]) -> Option<Signature> {
+    functions
+        .iter()
+        .cloned()
+        .chain(builtins())
+        .find(|s| s.name == name)
+}
+
+/// Functions, builtins and keywords starting with `prefix`, module functions first.
+pub fn complete(prefix: &str, functions: &[Signature]) -> Vec<Item> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let functions = functions.iter().map(|s| Item {
+        label: s.name.clone(),
+        detail: s.to_string(),
+        kind: ItemKind::Function,
+    });
+    let builtins = builtins().map(|s| Item {
+        label: s.name.clone(),
+        detail: s.to_string(),
+        kind: ItemKind::Builtin,
+    });
+    let keywords = keywords().iter().map(|k| Item {
+        label: k.to_string(),
+        detail: "keyword".into(),
+        kind: ItemKind::Keyword,
+    });
+
+    let mut items = functions
+        .chain(builtins)
+        .chain(keywords)
+        .filter(|i| i.label.starts_with(prefix) && i.label != prefix)
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| (a.kind, &a.label).cmp(&(b.kind, &b.label)));
+    items.dedup_by(|a, b| a.label == b.label);
+    items.truncate(COMPLETION_LIMIT);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(name: &str, params: &[(&str, &str)], ret: Option<&str>) -> Signature {
+        Signature {
+            name: name.into(),
+            params: params
+                .iter()
+                .map(|(n, t)| (n.to_string(), t.to_string()))
+                .collect(),
+            ret: ret.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn prefix_is_the_word_left_of_the_column() {
+        assert_eq!(prefix("  return osc_fr", 15), "osc_fr");
+        assert_eq!(prefix("  return osc_fr", 12), "osc");
+        assert_eq!(prefix("a + b", 4), "");
+        assert_eq!(prefix("", 0), "");
+        assert_eq!(prefix("x = pitch", 99), "pitch");
+    }
+
+    #[test]
+    fn call_is_the_innermost_open_call() {
+        assert_eq!(call("return mix(a, sin(b", 19), Some("sin"));
+        assert_eq!(call("return mix(a, sin(b), ", 22), Some("mix"));
+        assert_eq!(call("return mix (a", 13), Some("mix"));
+        assert_eq!(call("return (a + b", 13), None);
+        assert_eq!(call("return a", 8), None);
+    }
+
+    #[test]
+    fn signatures_come_from_the_syntax_tree() {
+        let source = "// fn commented(x: Num) -> Num\n\
+                      fn mix(a: Num, b: Num) -> Num {\n  return a + b;\n}\n\
+                      fn main(input: Num) -> Num {\n  return mix(input, 1.0);\n}\n";
+        assert_eq!(
+            signatures(source),
+            [
+                signature("mix", &[("a", "Num"), ("b", "Num")], Some("Num")),
+                signature("main", &[("input", "Num")], Some("Num")),
+            ]
+        );
+        assert_eq!(
+            signatures(source)[0].to_string(),
+            "fn mix(a: Num, b: Num) -> Num"
+        );
+    }
+
+    #[test]
+    fn complete_lists_module_functions_first() {
+        let functions = [signature("sine_env", &[("x", "Num")], Some("Num"))];
+        let items = complete("si", &functions);
+        assert_eq!(items[0].label, "sine_env");
+        assert_eq!(items[0].kind, ItemKind::Function);
+        assert!(items.iter().all(|i| i.label.starts_with("si")));
+        assert!(complete("", &functions).is_empty());
+        assert!(complete("sine_env", &functions).is_empty());
+    }
+
+    #[test]
+    fn lookup_prefers_module_functions() {
+        let functions = [signature("main", &[("input", "Num")], Some("Num"))];
+        assert_eq!(lookup("main", &functions), Some(functions[0].clone()));
+        assert_eq!(lookup("undefined_function", &functions), None);
+    }
+}
@@ -0,0 +1,153 @@
+use std::ops::Range;
+
+use iced::widget::text_editor::{Action, Motion};
+
+/// A position in the editor, as a line and a byte range within that line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An error produced by the lexer, parser or executor, mapped onto the module source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Creates a diagnostic pointing at the given byte range of `source`.
+    pub fn at(source: &str, range: Range<usize>, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span: Some(Span::from_range(source, range)),
+        }
+    }
+
+    pub fn from_error(source: &str, label: &str, error: &bs::error::Error) -> Self {
+        Self::at(
+            source,
+            error.range.clone(),
+            error.format_with(source, label, false),
+        )
+    }
+
+    /// First line of the message, used in the clickable error list.
+    pub fn summary(&self) -> String {
+        let message = self.message.lines().next().unwrap_or_default();
+        match self.span {
+            Some(span) => format!("{}:{} {}", span.line + 1, span.start + 1, message),
+            None => message.to_string(),
+        }
+    }
+
+    /// Editor actions that move the cursor to the start of the span.
+    pub fn goto(&self, source: &str) -> Vec<Action> {
+        match self.span {
+            Some(span) => move_to(source, span.line, span.start),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Editor actions moving the cursor to the byte `column` of `line` in `source`.
+pub fn move_to(source: &str, line: usize, column: usize) -> Vec<Action> {
+    let text = source.lines().nth(line).unwrap_or_default();
+    let column = text
+        .get(..column)
+        .map(|l| l.chars().count())
+        .unwrap_or_default();
+
+    let mut actions = vec![Action::Move(Motion::DocumentStart)];
+    actions.extend((0..line).map(|_| Action::Move(Motion::Down)));
+    actions.push(Action::Move(Motion::Home));
+    actions.extend((0..column).map(|_| Action::Move(Motion::Right)));
+    actions
+}
+
+impl Span {
+    /// Maps a byte range of `source` onto a single line, clamping ranges that span
+    /// several lines to the end of the first one.
+    pub fn from_range(source: &str, range: Range<usize>) -> Self {
+        let mut offset = range.start.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = source[..offset].matches('\n').count();
+        let line_start = source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[offset..]
+            .find('\n')
+            .map(|i| i + offset)
+            .unwrap_or(source.len());
+
+        let start = offset - line_start;
+        let end = range.end.clamp(offset, line_end) - line_start;
+        // always mark at least one character, so errors at the end of a line stay visible
+        let end = if end == start { start + 1 } else { end };
+
+        Self { line, start, end }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_on_later_line() {
+        let source = "fn main() {\n  return foo;\n}";
+        let start = source.find("foo").unwrap();
+        let span = Span::from_range(source, start..start + 3);
+        assert_eq!(
+            span,
+            Span {
+                line: 1,
+                start: 9,
+                end: 12
+            }
+        );
+    }
+
+    #[test]
+    fn span_clamped_to_first_line() {
+        let span = Span::from_range("let a\nlet b", 4..10);
+        assert_eq!(
+            span,
+            Span {
+                line: 0,
+                start: 4,
+                end: 5
+            }
+        );
+    }
+
+    #[test]
+    fn empty_span_marks_one_character() {
+        let span = Span::from_range("abc\n", 3..3);
+        assert_eq!(span.start, 3);
+        assert_eq!(span.end, 4);
+    }
+
+    #[test]
+    fn span_past_the_end_and_inside_a_character() {
+        let span = Span::from_range("ä", 1..1);
+        assert_eq!((span.line, span.start), (0, 0));
+        let span = Span::from_range("ab", 10..12);
+        assert_eq!((span.line, span.start, span.end), (0, 2, 3));
+    }
+
+    #[test]
+    fn summary_has_one_based_position() {
+        let diagnostic = Diagnostic::at("a\nbc d", 5..6, "unknown d\nmore detail");
+        assert_eq!(diagnostic.summary(), "2:4 unknown d");
+    }
+}
@@ -0,0 +1,173 @@
+use crate::tokens::{self, Kind, Token};
+
+const INDENT: &str = "  ";
+/// Blank lines kept in a row.
+const BLANK_LINES: usize = 1;
+
+fn opens(token: &Token) -> bool {
+    matches!(token.text, "{" | "(" | "[")
+}
+
+fn closes(token: &Token) -> bool {
+    matches!(token.text, "}" | ")" | "]")
+}
+
+/// Whether `token` is a sign or negation, which sticks to the operand after it.
+fn is_unary(before: Option<&Token>, token: &Token) -> bool {
+    if !matches!(token.text, "-" | "!") {
+        return false;
+    }
+    match before {
+        None => true,
+        Some(b) => {
+            matches!(b.kind, Kind::Operator | Kind::Keyword)
+                || matches!(b.text, "(" | "[" | "{" | "," | ";")
+        }
+    }
+}
+
+/// Whether a space goes between `a` and `b` on the same line. `joined` is set when
+/// the source has no whitespace between them.
+fn space(a: &Token, b: &Token, unary: bool, joined: bool) -> bool {
+    let callable = matches!(a.kind, Kind::Function | Kind::Identifier | Kind::Type);
+    if b.kind == Kind::Comment {
+        true
+    } else if matches!(a.text, "(" | "[" | ".")
+        || matches!(b.text, ")" | "]" | "," | ";" | "." | ":")
+    {
+        false
+    } else if b.text == "(" || b.text == "[" {
+        !(callable || a.text == ")" || a.text == "]")
+    } else if unary {
+        false
+    } else {
+        // operators the lexer split, like `->` or `==`, stay together
+        !(joined && a.kind == Kind::Operator && b.kind == Kind::Operator)
+    }
+}
+
+/// Whether `a` and `b` lex to the same tokens, so they only differ in whitespace that
+/// doesn't separate tokens.
+fn same_code(a: &str, b: &str) -> bool {
+    let lexed = |s| {
+        tokens::tokenize(s)
+            .into_iter()
+            .map(|t| (t.kind, t.text.trim_end()))
+            .collect::<Vec<_>>()
+    };
+    lexed(a) == lexed(b)
+}
+
+/// Pretty-prints a module from its tokens: one space between tokens where one belongs,
+/// indentation by nesting and at most one blank line in a row. Line breaks and comments
+/// are kept as written, so the result only differs in whitespace.
+pub fn format(source: &str) -> Result<String, String> {
+    let tokens = tokens::tokenize(source);
+    let mut formatted = String::with_capacity(source.len());
+    let mut depth = 0usize;
+    let mut end = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let gap = &source[end..token.range.start];
+        if !gap.trim().is_empty() {
+            let line = source[..end].lines().count().max(1);
+            return Err(format!("unexpected {:?} at line {line}", gap.trim()));
+        }
+
+        let before = i.checked_sub(1).map(|b| &tokens[b]);
+        if closes(token) {
+            depth = depth.saturating_sub(1);
+        }
+        let newlines = gap.matches('\n').count();
+        match before {
+            None => (),
+            Some(_) if newlines > 0 => {
+                formatted.push_str(&"\n".repeat(newlines.min(BLANK_LINES + 1)));
+                formatted.push_str(&INDENT.repeat(depth));
+            }
+            Some(b) => {
+                let unary = is_unary(i.checked_sub(2).map(|p| &tokens[p]), b);
+                if space(b, token, unary, gap.is_empty()) {
+                    formatted.push(' ');
+                }
+            }
+        }
+
+        formatted.push_str(token.text.trim_end());
+        if opens(token) {
+            depth += 1;
+        }
+        end = token.range.end;
+    }
+    if !source[end..].trim().is_empty() {
+        return Err(format!("unexpected {:?} at the end", source[end..].trim()));
+    }
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+
+    // the formatter must never touch the code itself
+    if !same_code(&formatted, source) {
+        return Err("formatting would change the code".into());
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATTED: &str = "// @description test
+fn env(x: Num) -> Num {
+  // falls from 1 to 0
+  return 1.0 / (1.0 + x * 20.0);
+}
+
+fn main(input: Num) -> Num {
+  let pitch = -200.0;
+  return sin(input * pitch) * env(input); // trailing
+}
+";
+
+    #[test]
+    fn formats_spacing_and_indentation() {
+        let messy = "// @description test
+fn env( x :Num )->Num{
+      // falls from 1 to 0
+  return 1.0/(1.0+x*20.0);
+}
+
+
+
+fn main(input: Num) -> Num {
+let pitch = - 200.0;
+    return sin (input*pitch)*env(input);   // trailing
+}";
+        assert_eq!(format(messy).unwrap(), FORMATTED);
+    }
+
+    #[test]
+    fn formatting_twice_changes_nothing() {
+        assert_eq!(format(FORMATTED).unwrap(), FORMATTED);
+        let once = format("fn main(a:Num)->Num{return a*2.0;}").unwrap();
+        assert_eq!(format(&once).unwrap(), once);
+    }
+
+    #[test]
+    fn comments_are_kept() {
+        let formatted = format("//a\nfn main() { // b\n  return 1.0; //c\n}\n// d").unwrap();
+        for comment in ["//a", "// b", "//c", "// d"] {
+            assert!(
+                formatted.contains(comment),
+                "{comment} missing in {formatted}"
+            );
+        }
+    }
+
+    #[test]
+    fn code_is_compared_by_tokens() {
+        assert!(same_code("let x=1.0;", "let x = 1.0;"));
+        assert!(!same_code("let x = 1.0;", "letx = 1.0;"));
+        assert!(!same_code("// a b", "// ab"));
+    }
+}
@@ -0,0 +1,88 @@
+use std::ops::Range;
+
+use iced::advanced::text::highlighter::{self, Format};
+use iced::{Color, Font, Theme};
+
+use crate::diagnostic::Span;
+use crate::tokens::{self, Kind};
+
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Settings {
+    pub diagnostics: Vec<Span>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    Token(Kind),
+    Error,
+}
+
+pub fn to_format(highlight: &Highlight, theme: &Theme) -> Format<Font> {
+    let color = match highlight {
+        Highlight::Token(kind) => match kind {
+            Kind::Keyword => Some(theme.extended_palette().secondary.strong.color),
+            Kind::Type => Some(theme.extended_palette().success.base.color),
+            Kind::Number => Some(Color::from_rgb(1.0, 0.7, 0.4)),
+            Kind::Operator => Some(Color::from_rgb(0.8, 0.8, 0.6)),
+            Kind::Function => Some(Color::from_rgb(0.6, 0.7, 1.0)),
+            Kind::Comment => Some(theme.extended_palette().primary.base.color),
+            Kind::Identifier | Kind::Punctuation => None,
+        },
+        Highlight::Error => Some(theme.palette().danger),
+    };
+    Format { color, font: None }
+}
+
+/// Highlights the module source in the [`iced::widget::TextEditor`].
+pub struct Highlighter {
+    settings: Settings,
+    current_line: usize,
+}
+
+impl highlighter::Highlighter for Highlighter {
+    type Settings = Settings;
+    type Highlight = Highlight;
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, Highlight)>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            settings: settings.clone(),
+            current_line: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.settings = new_settings.clone();
+        self.current_line = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        let index = self.current_line;
+        self.current_line += 1;
+
+        // errors come last so they are drawn over the token colors
+        tokens::tokenize(line)
+            .into_iter()
+            .map(|token| (token.range, Highlight::Token(token.kind)))
+            .chain(
+                self.settings
+                    .diagnostics
+                    .iter()
+                    .filter(|span| span.line == index)
+                    .map(|span| {
+                        let end = span.end.min(line.len());
+                        (span.start.min(end)..end, Highlight::Error)
+                    }),
+            )
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
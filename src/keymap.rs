@@ -0,0 +1,285 @@
+use std::fs;
+
+use iced::keyboard::{key::Named, Key, Modifiers};
+
+use crate::project;
+
+/// Actions that can be bound to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Save,
+    Compile,
+    PlayStop,
+    NewModule,
+    QuickOpen,
+    Find,
+    Complete,
+    Format,
+    Undo,
+    Redo,
+}
+
+/// Names of the commands in the keymap file.
+const COMMANDS: &[(&str, Command)] = &[
+    ("save", Command::Save),
+    ("compile", Command::Compile),
+    ("play_stop", Command::PlayStop),
+    ("new_module", Command::NewModule),
+    ("quick_open", Command::QuickOpen),
+    ("find", Command::Find),
+    ("complete", Command::Complete),
+    ("format", Command::Format),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+];
+
+const DEFAULTS: &[(&str, Command)] = &[
+    ("ctrl+s", Command::Save),
+    ("ctrl+enter", Command::Compile),
+    ("space", Command::PlayStop),
+    ("ctrl+n", Command::NewModule),
+    ("ctrl+p", Command::QuickOpen),
+    ("ctrl+shift+f", Command::Find),
+    ("tab", Command::Complete),
+    ("ctrl+shift+i", Command::Format),
+    ("ctrl+z", Command::Undo),
+    ("ctrl+shift+z", Command::Redo),
+    ("ctrl+y", Command::Redo),
+];
+
+const NAMED_KEYS: &[(&str, Named)] = &[
+    ("enter", Named::Enter),
+    ("space", Named::Space),
+    ("tab", Named::Tab),
+    ("escape", Named::Escape),
+    ("backspace", Named::Backspace),
+    ("delete", Named::Delete),
+    ("up", Named::ArrowUp),
+    ("down", Named::ArrowDown),
+    ("left", Named::ArrowLeft),
+    ("right", Named::ArrowRight),
+    ("home", Named::Home),
+    ("end", Named::End),
+    ("f1", Named::F1),
+    ("f2", Named::F2),
+    ("f3", Named::F3),
+    ("f4", Named::F4),
+    ("f5", Named::F5),
+    ("f6", Named::F6),
+    ("f7", Named::F7),
+    ("f8", Named::F8),
+    ("f9", Named::F9),
+    ("f10", Named::F10),
+    ("f11", Named::F11),
+    ("f12", Named::F12),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum BindingKey {
+    Named(Named),
+    Character(String),
+}
+
+/// A key with the modifiers that have to be held, `ctrl` is cmd on macOS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Binding {
+    key: BindingKey,
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+}
+
+impl Binding {
+    /// Parses bindings like `ctrl+shift+z` or `space`.
+    pub fn parse(binding: &str) -> Result<Self, String> {
+        let parts = binding
+            .split('+')
+            .map(|p| p.trim().to_lowercase())
+            .collect::<Vec<_>>();
+        let Some((key, modifiers)) = parts.split_last() else {
+            return Err("empty binding".into());
+        };
+
+        let key = match NAMED_KEYS.iter().find(|(name, _)| name == key) {
+            Some((_, named)) => BindingKey::Named(*named),
+            None if key.chars().count() == 1 => BindingKey::Character(key.clone()),
+            None => return Err(format!("unknown key {key:?}")),
+        };
+        let mut binding = Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in modifiers {
+            match modifier.as_str() {
+                "ctrl" | "cmd" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" => binding.alt = true,
+                _ => return Err(format!("unknown modifier {modifier:?}")),
+            }
+        }
+        Ok(binding)
+    }
+
+    fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        let key = match (key.as_ref(), &self.key) {
+            (Key::Named(named), BindingKey::Named(bound)) => named == *bound,
+            (Key::Character(c), BindingKey::Character(bound)) => c.to_lowercase() == *bound,
+            _ => false,
+        };
+        key && modifiers.command() == self.ctrl
+            && modifiers.shift() == self.shift
+            && modifiers.alt() == self.alt
+    }
+}
+
+/// Key bindings of the app commands.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Binding, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULTS
+            .iter()
+            .map(|(binding, command)| (Binding::parse(binding).unwrap(), *command))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Default bindings with the commands listed in the `keymap` file of the config
+    /// folder rebound. Each line of the file is `command = binding, binding`, leaving the
+    /// bindings empty unbinds the command and `#` starts a comment.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = project::config_path() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(path.join("keymap")) {
+            Ok(file) => Self::parse(&file),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Default bindings with those of a `keymap` file applied. A key bound in the file
+    /// runs only the command it is bound to there.
+    fn parse(file: &str) -> Result<Self, String> {
+        let mut keymap = Self::default();
+        for (i, line) in file.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |e: String| format!("keymap line {}: {e}", i + 1);
+            let Some((name, bindings)) = line.split_once('=') else {
+                return Err(error("expected command = bindings".into()));
+            };
+            let Some((_, command)) = COMMANDS.iter().find(|(n, _)| *n == name.trim()) else {
+                return Err(error(format!("unknown command {:?}", name.trim())));
+            };
+
+            keymap.bindings.retain(|(_, c)| c != command);
+            for binding in bindings.split(',').filter(|b| !b.trim().is_empty()) {
+                let binding = Binding::parse(binding).map_err(error)?;
+                keymap.bindings.retain(|(b, _)| *b != binding);
+                keymap.bindings.push((binding, *command));
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Command bound to the key press. Keys a widget already used, like text typed into
+    /// the editor, only trigger bindings with ctrl or alt held.
+    pub fn resolve(&self, key: &Key, modifiers: Modifiers, captured: bool) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(b, _)| b.matches(key, modifiers) && (!captured || b.ctrl || b.alt))
+            .map(|(_, command)| *command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys_and_modifiers() {
+        assert_eq!(
+            Binding::parse("Ctrl + Shift + Z"),
+            Ok(Binding {
+                key: BindingKey::Character("z".into()),
+                ctrl: true,
+                shift: true,
+                alt: false,
+            })
+        );
+        assert_eq!(
+            Binding::parse("alt+f5"),
+            Ok(Binding {
+                key: BindingKey::Named(Named::F5),
+                ctrl: false,
+                shift: false,
+                alt: true,
+            })
+        );
+        assert_eq!(Binding::parse("cmd+s"), Binding::parse("ctrl+s"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!(Binding::parse("").is_err());
+        assert!(Binding::parse("ctrl+pageup").is_err());
+        assert!(Binding::parse("super+s").is_err());
+        assert!(Binding::parse("ctrl+").is_err());
+    }
+
+    #[test]
+    fn defaults_parse() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.bindings.len(), DEFAULTS.len());
+    }
+
+    #[test]
+    fn resolves_bindings_with_modifiers_held() {
+        let keymap = Keymap::default();
+        let z = Key::Character("Z".into());
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+        assert_eq!(keymap.resolve(&z, ctrl_shift, true), Some(Command::Redo));
+        assert_eq!(
+            keymap.resolve(&z, Modifiers::CTRL, true),
+            Some(Command::Undo)
+        );
+        assert_eq!(keymap.resolve(&z, Modifiers::empty(), false), None);
+    }
+
+    #[test]
+    fn file_bindings_replace_defaults_on_the_same_key() {
+        let keymap = Keymap::parse("# rebinds ctrl+s\ncompile = ctrl+s\nundo =\n").unwrap();
+        let s = Key::Character("s".into());
+        let z = Key::Character("z".into());
+        assert_eq!(
+            keymap.resolve(&s, Modifiers::CTRL, true),
+            Some(Command::Compile)
+        );
+        assert_eq!(keymap.resolve(&z, Modifiers::CTRL, true), None);
+        assert!(!keymap.bindings.iter().any(|(_, c)| *c == Command::Save));
+
+        assert!(Keymap::parse("compile ctrl+s").is_err());
+        assert!(Keymap::parse("launch = ctrl+l").is_err());
+        assert!(Keymap::parse("compile = ctrl+pageup").is_err());
+    }
+
+    #[test]
+    fn captured_keys_need_ctrl_or_alt() {
+        let keymap = Keymap::default();
+        let space = Key::Named(Named::Space);
+        assert_eq!(
+            keymap.resolve(&space, Modifiers::empty(), false),
+            Some(Command::PlayStop)
+        );
+        assert_eq!(keymap.resolve(&space, Modifiers::empty(), true), None);
+    }
+}
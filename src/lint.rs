@@ -0,0 +1,142 @@
+use std::f64::consts::PI;
+
+/// Largest mean before the output counts as DC offset.
+const DC_LIMIT: f64 = 0.1;
+/// Peak below which the output counts as silent, about -80 dB.
+pub const SILENCE: f32 = 0.0001;
+/// Dominant frequency, as a fraction of the sample rate, above which aliasing is likely.
+const ALIASING: f64 = 0.3;
+
+/// Problems a listener would hear in the rendered `samples`, played at `rate`.
+pub fn lint(samples: &[f32], rate: usize) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let seconds = |i: usize| i as f64 / rate as f64;
+
+    let broken = samples.iter().filter(|s| !s.is_finite()).count();
+    if let Some(first) = samples.iter().position(|s| !s.is_finite()) {
+        warnings.push(format!(
+            "{broken} samples are NaN or infinite, the first at {:.3}s",
+            seconds(first)
+        ));
+    }
+    let samples = samples
+        .iter()
+        .copied()
+        .filter(|s| s.is_finite())
+        .collect::<Vec<_>>();
+    if samples.is_empty() {
+        return warnings;
+    }
+
+    let (peak_at, peak) = samples
+        .iter()
+        .map(|s| s.abs())
+        .enumerate()
+        .fold((0, 0.0), |max, (i, s)| if s > max.1 { (i, s) } else { max });
+    if peak < SILENCE {
+        warnings.push("output is silent".into());
+        return warnings;
+    }
+    let clipped = samples.iter().filter(|s| s.abs() > 1.0).count();
+    if clipped > 0 {
+        warnings.push(format!(
+            "{clipped} samples exceed ±1.0 and will clip, peak {peak:.2} at {:.3}s",
+            seconds(peak_at)
+        ));
+    }
+
+    let mean = samples.iter().map(|s| *s as f64).sum::<f64>() / samples.len() as f64;
+    if mean.abs() > DC_LIMIT {
+        warnings.push(format!(
+            "DC offset of {mean:.2}, the output is not centered on 0"
+        ));
+    }
+
+    // a sine of frequency f changes by 2 sin(pi f / rate) of its level each sample
+    let energy = samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>();
+    let change = samples
+        .windows(2)
+        .map(|w| (w[1] as f64 - w[0] as f64).powi(2))
+        .sum::<f64>();
+    let ratio = (change / energy).sqrt().min(2.0);
+    let frequency = (ratio / 2.0).asin() / PI;
+    if samples.len() > 1 && frequency > ALIASING {
+        warnings.push(format!(
+            "most energy is around {:.0} Hz, close to the {} Hz Nyquist limit, \
+             higher frequencies fold back as aliasing",
+            frequency * rate as f64,
+            rate / 2
+        ));
+    }
+
+    warnings
+}
+
+/// One second of a sine at `frequency` with the peak `level`, sampled at `rate`.
+#[cfg(test)]
+pub fn sine(frequency: f64, level: f32, rate: usize) -> Vec<f32> {
+    (0..rate)
+        .map(|i| (2.0 * PI * frequency * i as f64 / rate as f64).sin() as f32 * level)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: usize = 48000;
+
+    #[test]
+    fn clean_sine_has_no_warnings() {
+        assert!(lint(&sine(440.0, 0.5, RATE), RATE).is_empty());
+        assert!(lint(&[], RATE).is_empty());
+    }
+
+    #[test]
+    fn silence_is_reported_alone() {
+        assert_eq!(lint(&[0.0; 100], RATE), ["output is silent"]);
+        assert_eq!(lint(&[0.00001, 0.2 * SILENCE], RATE), ["output is silent"]);
+    }
+
+    #[test]
+    fn clipping_names_the_peak() {
+        let warnings = lint(&sine(440.0, 1.5, RATE), RATE);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("exceed ±1.0"), "{warnings:?}");
+        assert!(warnings[0].contains("peak 1.50"), "{warnings:?}");
+    }
+
+    #[test]
+    fn broken_samples_are_counted_and_skipped() {
+        let mut samples = sine(440.0, 0.5, RATE);
+        samples[RATE / 2] = f32::NAN;
+        samples[RATE / 2 + 1] = f32::INFINITY;
+        let warnings = lint(&samples, RATE);
+        assert_eq!(
+            warnings,
+            ["2 samples are NaN or infinite, the first at 0.500s"]
+        );
+        assert_eq!(lint(&[f32::NAN], RATE).len(), 1);
+    }
+
+    #[test]
+    fn dc_offset() {
+        let samples = sine(440.0, 0.5, RATE)
+            .into_iter()
+            .map(|s| s + 0.3)
+            .collect::<Vec<_>>();
+        let warnings = lint(&samples, RATE);
+        assert_eq!(
+            warnings,
+            ["DC offset of 0.30, the output is not centered on 0"]
+        );
+    }
+
+    #[test]
+    fn energy_near_nyquist_hints_at_aliasing() {
+        let warnings = lint(&sine(20000.0, 0.5, RATE), RATE);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("aliasing"), "{warnings:?}");
+        assert!(lint(&sine(5000.0, 0.5, RATE), RATE).is_empty());
+    }
+}
@@ -2,13 +2,25 @@ mod graph;
 mod modules;
 mod widgets;
 mod audio;
+mod cli;
+mod completion;
+mod diagnostic;
+mod format;
+mod highlighter;
+mod keymap;
+mod lint;
+mod project;
+mod tokens;
 
-use iced::{theme::palette, Color, Command, Element, Settings};
+use iced::widget::text_editor::{Action, Edit};
+use iced::{keyboard, theme::palette, widget, window, Color, Command, Element, Event, Settings};
+
+use keymap::Keymap;
 
 use modules::ModuleMessage;
 use modules::Modules;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 #[derive(Clone, Copy, Debug)]
@@ -25,7 +37,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     //     ("Sequencer".into(), Page::Sequencer),
     // ];
 
-    let settings = Settings::default();
+    let args = std::env::args_os().skip(1).collect::<Vec<_>>();
+    if let Some(result) = cli::run(&args) {
+        if let Err(e) = result {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // the project folder can be given as the first argument
+    let project = args
+        .first()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("./"));
+    let project = project::open(&project, false)?;
+
+    let settings = Settings {
+        flags: project,
+        window: window::Settings {
+            // unsaved buffers are written to the recovery folder before closing
+            exit_on_close_request: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
 
     <App as iced::Application>::run(settings)?;
 
@@ -38,30 +74,70 @@ pub enum Message {
     ButtonClick,
     Editor(ModuleMessage),
     Tick,
+    CloseRequested(window::Id),
+    /// a key press and whether a widget already used it
+    KeyPressed(keyboard::Key, keyboard::Modifiers, bool),
+    ModifiersChanged(keyboard::Modifiers),
+    ProjectInput(String),
+    OpenProject(PathBuf),
+    NewProject,
 }
 
 /// The [`App`] stores application-specific state.
 pub struct App {
     editor: Modules,
     time: f32,
+    project: PathBuf,
+    project_input: String,
+    project_error: Option<String>,
+    recent: Vec<PathBuf>,
+    keymap: Keymap,
+    /// modifiers currently held, to keep shortcuts out of the editor
+    modifiers: keyboard::Modifiers,
 }
 
 /// Implement [`cosmic::Application`] to integrate with COSMIC.
 impl iced::Application for App {
     type Executor = iced::executor::Default;
-    type Flags = Vec<(String, Page)>;
+    type Flags = PathBuf;
     type Message = Message;
     // type Theme = theme::Theme;
     type Theme = iced::Theme;
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
-        iced::time::every(Duration::from_millis(50)).map(|_| Message::Tick)
+        let events = iced::event::listen_with(|event, status| match event {
+            Event::Window(id, window::Event::CloseRequested) => Some(Message::CloseRequested(id)),
+            Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => Some(
+                Message::KeyPressed(key, modifiers, status == iced::event::Status::Captured),
+            ),
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(Message::ModifiersChanged(modifiers))
+            }
+            _ => None,
+        });
+        let tick = iced::time::every(Duration::from_millis(50)).map(|_| Message::Tick);
+
+        iced::Subscription::batch([tick, events, self.editor.subscription()])
     }
 
     /// Creates the application, and optionally emits command on initialize.
-    fn new(_input: Self::Flags) -> (Self, Command<Self::Message>) {
-        let editor = Modules::new(PathBuf::from("./").canonicalize().unwrap());
-        let app = App { editor, time: 0.0 };
+    fn new(project: Self::Flags) -> (Self, Command<Self::Message>) {
+        let _ = project::add_recent(&project);
+        let editor = Modules::new(project.clone());
+        let (keymap, keymap_error) = match Keymap::load() {
+            Ok(keymap) => (keymap, None),
+            Err(e) => (Keymap::default(), Some(e)),
+        };
+        let app = App {
+            editor,
+            time: 0.0,
+            project,
+            project_input: String::new(),
+            project_error: keymap_error,
+            recent: project::recent(),
+            keymap,
+            modifiers: keyboard::Modifiers::default(),
+        };
 
         (app, iced::Command::none())
     }
@@ -70,8 +146,29 @@ impl iced::Application for App {
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
             Message::ButtonClick => println!("clicked"),
+            // ctrl+enter and similar shortcuts must not also break the line
+            Message::Editor(
+                ModuleMessage::Editor(Action::Edit(Edit::Enter))
+                | ModuleMessage::SplitEditor(Action::Edit(Edit::Enter)),
+            ) if self.is_shortcut(keyboard::key::Named::Enter) => {}
             Message::Editor(e) => self.editor.update(e),
-            Message::Tick => (),
+            Message::KeyPressed(key, modifiers, captured) => {
+                if let Some(command) = self.keymap.resolve(&key, modifiers, captured) {
+                    return self.run(command);
+                }
+            }
+            Message::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            Message::Tick => self.editor.tick(),
+            Message::CloseRequested(id) => {
+                self.editor.write_recovery();
+                return window::close(id);
+            }
+            Message::ProjectInput(input) => self.project_input = input,
+            Message::OpenProject(path) => self.open_project(&path, false),
+            Message::NewProject => {
+                let path = PathBuf::from(&self.project_input);
+                self.open_project(&path, true)
+            }
         };
         self.time += 0.5;
 
@@ -129,10 +226,109 @@ impl iced::Application for App {
 
         // element
 
-        self.editor.view()
+        widget::column([self.project_bar(), self.editor.view()]).into()
     }
 
     fn title(&self) -> String {
-        String::from("MkSND")
+        match self.project.file_name() {
+            Some(name) => format!("MkSND - {}", name.to_string_lossy()),
+            None => String::from("MkSND"),
+        }
+    }
+}
+
+impl App {
+    /// Whether pressing `key` with the held modifiers runs a command.
+    fn is_shortcut(&self, key: keyboard::key::Named) -> bool {
+        let key = keyboard::Key::Named(key);
+        self.keymap.resolve(&key, self.modifiers, true).is_some()
+    }
+
+    /// Runs a command bound in the [`Keymap`].
+    fn run(&mut self, command: keymap::Command) -> Command<Message> {
+        use keymap::Command as C;
+        use widget::text_input;
+
+        let message = match command {
+            C::Save => ModuleMessage::Save,
+            C::Compile => ModuleMessage::CompileModule,
+            C::PlayStop if audio::get().is_some_and(|e| e.is_playing()) => ModuleMessage::Stop,
+            C::PlayStop => ModuleMessage::TestModule,
+            C::Undo => ModuleMessage::Undo,
+            C::Redo => ModuleMessage::Redo,
+            C::Complete => ModuleMessage::Complete(None),
+            C::Format => ModuleMessage::Format,
+            C::NewModule => {
+                return text_input::focus(text_input::Id::new(modules::ADD_MODULE_INPUT))
+            }
+            C::Find => {
+                self.editor.update(ModuleMessage::ToggleFind(true));
+                return text_input::focus(text_input::Id::new(modules::FIND_INPUT));
+            }
+            C::QuickOpen => {
+                let search = text_input::Id::new(modules::SEARCH_INPUT);
+                return Command::batch([
+                    text_input::focus(search.clone()),
+                    text_input::select_all(search),
+                ]);
+            }
+        };
+        self.editor.update(message);
+        Command::none()
+    }
+
+    /// Switches to another project folder, keeping unsaved buffers of the current
+    /// one in its recovery folder.
+    fn open_project(&mut self, path: &Path, create: bool) {
+        match project::open(path, create) {
+            Ok(project) => {
+                self.editor.write_recovery();
+                self.editor = Modules::new(project.clone());
+                if create {
+                    self.editor.seed_templates();
+                }
+                let _ = project::add_recent(&project);
+                self.recent = project::recent();
+                self.project = project;
+                self.project_input.clear();
+                self.project_error = None;
+            }
+            Err(e) => self.project_error = Some(e.to_string()),
+        }
+    }
+
+    fn project_bar(&self) -> Element<'_, Message> {
+        let input = widget::text_input("project folder", &self.project_input)
+            .on_input(Message::ProjectInput)
+            .on_submit(Message::OpenProject(PathBuf::from(&self.project_input)));
+        let open = widget::button(widget::text("OPEN"))
+            .on_press(Message::OpenProject(PathBuf::from(&self.project_input)));
+        let new = widget::button(widget::text("NEW")).on_press(Message::NewProject);
+
+        let recent = self
+            .recent
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let recent = widget::pick_list(recent, None::<String>, |p| {
+            Message::OpenProject(PathBuf::from(p))
+        })
+        .placeholder("recent projects");
+
+        let error = widget::text(self.project_error.clone().unwrap_or_default())
+            .style(iced::theme::Text::Color(Color::from_rgb(1.0, 0.5, 0.5)));
+
+        widget::row([
+            widget::text(self.project.to_string_lossy()).into(),
+            input.into(),
+            open.into(),
+            new.into(),
+            recent.into(),
+            error.into(),
+        ])
+        .spacing(iced::Pixels(5.0))
+        .padding(iced::Padding::from([10.0, 10.0, 0.0, 10.0]))
+        .align_items(iced::Alignment::Center)
+        .into()
     }
 }
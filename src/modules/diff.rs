@@ -0,0 +1,85 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Line diff turning `old` into `new`, from their longest common subsequence.
+pub fn diff(old: &str, new: &str) -> Vec<Line> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // common[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(Line::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| Line::Removed(l.to_string())));
+    lines.extend(new[j..].iter().map(|l| Line::Added(l.to_string())));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(line: &str) -> Line {
+        Line::Same(line.into())
+    }
+
+    #[test]
+    fn equal_texts_are_the_same() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), [same("a"), same("b")]);
+        assert_eq!(diff("", ""), []);
+    }
+
+    #[test]
+    fn changed_line_is_removed_then_added() {
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nx\nc\n"),
+            [
+                same("a"),
+                Line::Removed("b".into()),
+                Line::Added("x".into()),
+                same("c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn insertions_and_deletions_at_the_ends() {
+        assert_eq!(
+            diff("b\nc\n", "a\nb\n"),
+            [
+                Line::Added("a".into()),
+                same("b"),
+                Line::Removed("c".into())
+            ]
+        );
+        assert_eq!(diff("", "a\n"), [Line::Added("a".into())]);
+        assert_eq!(diff("a\n", ""), [Line::Removed("a".into())]);
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+
+use crate::lint::SILENCE;
+
+/// Fraction the fundamental may be off by when no tolerance is given.
+const FUNDAMENTAL_TOLERANCE: f64 = 0.03;
+/// Lowest and highest fundamental that can be detected, in Hz.
+const PITCH_RANGE: (f64, f64) = (20.0, 2000.0);
+/// Seconds analysed to find the fundamental.
+const PITCH_WINDOW: f64 = 0.5;
+
+/// A check on the TEST render of a module, written in its header as `// @expect ...`:
+///
+/// ```text
+/// // @expect peak below 0.9
+/// // @expect silent after 1.5 s
+/// // @expect fundamental near 55 Hz
+/// // @expect fundamental near 55 ± 2
+/// // @expect checksum 3f1c0a2e9b7d4410
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expectation {
+    PeakBelow(f32),
+    /// seconds after which the output stays silent
+    SilentAfter(f64),
+    /// frequency in Hz and how far off it may be
+    Fundamental(f64, f64),
+    /// hash of the render, to notice any change to a finished sound
+    Checksum(u64),
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::PeakBelow(peak) => write!(f, "peak below {peak}"),
+            Expectation::SilentAfter(seconds) => write!(f, "silent after {seconds}"),
+            Expectation::Fundamental(hz, tolerance) => {
+                write!(f, "fundamental near {hz} ± {tolerance}")
+            }
+            Expectation::Checksum(checksum) => write!(f, "checksum {checksum:016x}"),
+        }
+    }
+}
+
+impl Expectation {
+    pub fn parse(expectation: &str) -> Result<Self, String> {
+        // units may follow a number after a space, like "1.5 s" or "55 Hz ± 2 Hz"
+        let words = expectation
+            .split_whitespace()
+            .filter(|w| !w.eq_ignore_ascii_case("s") && !w.eq_ignore_ascii_case("hz"))
+            .collect::<Vec<_>>();
+        let number = |word: Option<&&str>| {
+            let word = word.ok_or_else(|| format!("{expectation:?} is missing a number"))?;
+            word.trim_end_matches(['s', 'S', 'H', 'h', 'z', 'Z'])
+                .parse::<f64>()
+                .map_err(|_| format!("{word:?} is not a number"))
+        };
+
+        match words.as_slice() {
+            ["peak", "below", ..] => Ok(Expectation::PeakBelow(number(words.get(2))? as f32)),
+            ["silent", "after", ..] => Ok(Expectation::SilentAfter(number(words.get(2))?)),
+            ["fundamental", "near", ..] => {
+                let hz = number(words.get(2))?;
+                let tolerance = match words.get(3) {
+                    Some(&"±") | Some(&"+-") => number(words.get(4))?,
+                    _ => hz * FUNDAMENTAL_TOLERANCE,
+                };
+                Ok(Expectation::Fundamental(hz, tolerance))
+            }
+            ["checksum", checksum] => u64::from_str_radix(checksum, 16)
+                .map(Expectation::Checksum)
+                .map_err(|_| format!("{checksum:?} is not a hex checksum")),
+            _ => Err(format!("unknown expectation {expectation:?}")),
+        }
+    }
+
+    /// Checks the render of `samples` at `rate`, the error describes what was measured.
+    pub fn check(&self, samples: &[f32], rate: usize) -> Result<(), String> {
+        match *self {
+            Expectation::PeakBelow(limit) => {
+                // NaN would be skipped by max, and sounds like anything but a quiet module
+                if samples.iter().any(|s| !s.is_finite()) {
+                    return Err("the render has NaN or infinite samples".into());
+                }
+                let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+                match peak < limit {
+                    true => Ok(()),
+                    false => Err(format!("peak is {peak:.3}")),
+                }
+            }
+            Expectation::SilentAfter(seconds) => {
+                let start = (seconds * rate as f64) as usize;
+                if start >= samples.len() {
+                    return Err(format!(
+                        "the render is only {:.2}s long, raise @length",
+                        samples.len() as f64 / rate as f64
+                    ));
+                }
+                match samples[start..].iter().position(|s| s.abs() >= SILENCE) {
+                    None => Ok(()),
+                    Some(i) => Err(format!("sound at {:.3}s", (start + i) as f64 / rate as f64)),
+                }
+            }
+            Expectation::Fundamental(hz, tolerance) => match fundamental(samples, rate) {
+                Some(found) if (found - hz).abs() <= tolerance => Ok(()),
+                Some(found) => Err(format!("fundamental is {found:.1} Hz")),
+                None => Err("no fundamental found".into()),
+            },
+            Expectation::Checksum(expected) => match checksum(samples) {
+                found if found == expected => Ok(()),
+                found => Err(format!("checksum is {found:016x}")),
+            },
+        }
+    }
+}
+
+/// Strongest period of the start of the render, found by autocorrelation.
+fn fundamental(samples: &[f32], rate: usize) -> Option<f64> {
+    let window = &samples[..samples.len().min((PITCH_WINDOW * rate as f64) as usize)];
+    let shortest = (rate as f64 / PITCH_RANGE.1) as usize;
+    let longest = ((rate as f64 / PITCH_RANGE.0) as usize).min(window.len() / 2);
+
+    let correlation = |lag: usize| {
+        let n = window.len() - lag;
+        (0..n)
+            .map(|i| window[i] as f64 * window[i + lag] as f64)
+            .sum::<f64>()
+            / n as f64
+    };
+    let energy = correlation(0);
+    if energy <= 0.0 || shortest >= longest {
+        return None;
+    }
+
+    // short lags always correlate, the search starts once the signal turned against
+    // itself, which for the highest pitches is before the shortest lag
+    let dip = (1..=longest).find(|lag| correlation(*lag) <= 0.0)?;
+    let correlations = (dip.max(shortest)..=longest)
+        .map(|lag| (lag, correlation(lag) / energy))
+        .collect::<Vec<_>>();
+    // the first lag that comes close to the best one, so octaves below are not picked
+    let best = correlations.iter().map(|(_, c)| *c).fold(0.0, f64::max);
+    let mut peak = correlations.iter().position(|(_, c)| *c >= best * 0.9)?;
+    while correlations
+        .get(peak + 1)
+        .is_some_and(|(_, c)| *c > correlations[peak].1)
+    {
+        peak += 1;
+    }
+    if best < 0.3 {
+        return None;
+    }
+
+    // fits a parabola through the peak and its neighbours for a lag between samples
+    let (lag, c) = correlations[peak];
+    let offset = match (peak.checked_sub(1), correlations.get(peak + 1)) {
+        (Some(before), Some((_, after))) => {
+            let before = correlations[before].1;
+            let curve = before - 2.0 * c + after;
+            match curve < 0.0 {
+                true => 0.5 * (before - after) / curve,
+                false => 0.0,
+            }
+        }
+        _ => 0.0,
+    };
+    Some(rate as f64 / (lag as f64 + offset))
+}
+
+/// FNV-1a hash of the render rounded to 6 decimals, so tiny float differences between
+/// machines don't change it.
+pub fn checksum(samples: &[f32]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for sample in samples {
+        let sample = (*sample as f64 * 1e6).round() as i64;
+        for byte in sample.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// Checks every `@expect` of the module against its render, returning the failures.
+pub fn failures(expectations: &[String], samples: &[f32], rate: usize) -> Vec<String> {
+    expectations
+        .iter()
+        .filter_map(|e| {
+            let expectation = match Expectation::parse(e) {
+                Ok(expectation) => expectation,
+                Err(error) => return Some(format!("@expect {e}: {error}")),
+            };
+            let error = expectation.check(samples, rate).err()?;
+            Some(format!("expected {expectation}, but {error}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::sine;
+
+    const RATE: usize = 48000;
+
+    fn parse(expectation: &str) -> Expectation {
+        Expectation::parse(expectation).unwrap()
+    }
+
+    #[test]
+    fn parses_each_form() {
+        assert_eq!(parse("peak below 0.9"), Expectation::PeakBelow(0.9));
+        assert_eq!(parse("silent after 1.5"), Expectation::SilentAfter(1.5));
+        assert_eq!(parse("silent after 1.5s"), Expectation::SilentAfter(1.5));
+        assert_eq!(parse("silent after 1.5 s"), Expectation::SilentAfter(1.5));
+        assert_eq!(
+            parse("fundamental near 55"),
+            Expectation::Fundamental(55.0, 1.65)
+        );
+        assert_eq!(
+            parse("fundamental near 55 Hz"),
+            Expectation::Fundamental(55.0, 1.65)
+        );
+        assert_eq!(
+            parse("fundamental near 55Hz"),
+            Expectation::Fundamental(55.0, 1.65)
+        );
+        assert_eq!(
+            parse("fundamental near 55 ± 2"),
+            Expectation::Fundamental(55.0, 2.0)
+        );
+        assert_eq!(
+            parse("fundamental near 55 Hz +- 2 Hz"),
+            Expectation::Fundamental(55.0, 2.0)
+        );
+        assert_eq!(
+            parse("checksum 3f1c0a2e9b7d4410"),
+            Expectation::Checksum(0x3f1c0a2e9b7d4410)
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_problem() {
+        assert!(Expectation::parse("peak below")
+            .unwrap_err()
+            .contains("missing a number"));
+        assert!(Expectation::parse("peak below loud")
+            .unwrap_err()
+            .contains("not a number"));
+        assert!(Expectation::parse("checksum xyz")
+            .unwrap_err()
+            .contains("not a hex checksum"));
+        assert!(Expectation::parse("louder than 3")
+            .unwrap_err()
+            .starts_with("unknown expectation"));
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for expectation in [
+            Expectation::PeakBelow(0.5),
+            Expectation::SilentAfter(2.0),
+            Expectation::Fundamental(440.0, 3.0),
+            Expectation::Checksum(0x00ff),
+        ] {
+            assert_eq!(parse(&expectation.to_string()), expectation);
+        }
+    }
+
+    #[test]
+    fn fundamental_of_sines() {
+        for hz in [55.0, 440.0, 1900.0] {
+            let found = fundamental(&sine(hz, 0.5, RATE), RATE).unwrap();
+            assert!(
+                (found - hz).abs() <= hz * FUNDAMENTAL_TOLERANCE,
+                "{hz} Hz found as {found} Hz"
+            );
+        }
+        assert_eq!(fundamental(&[0.0; RATE], RATE), None);
+    }
+
+    #[test]
+    fn checks_measure_the_render() {
+        let samples = sine(440.0, 0.5, RATE);
+        assert_eq!(parse("peak below 0.6").check(&samples, RATE), Ok(()));
+        assert_eq!(
+            parse("peak below 0.4").check(&samples, RATE),
+            Err("peak is 0.500".into())
+        );
+        let mut broken = samples.clone();
+        broken[100] = f32::NAN;
+        assert!(parse("peak below 0.6").check(&broken, RATE).is_err());
+        assert!(parse("silent after 0.5")
+            .check(&samples, RATE)
+            .unwrap_err()
+            .starts_with("sound at 0.500s"));
+        assert!(parse("silent after 2")
+            .check(&samples, RATE)
+            .unwrap_err()
+            .contains("raise @length"));
+        assert_eq!(parse("fundamental near 440").check(&samples, RATE), Ok(()));
+    }
+
+    #[test]
+    fn checksum_is_stable() {
+        // levels in steps of 1e-3, so a nudge never crosses a rounding boundary
+        let samples = (0..2000)
+            .map(|i| (i as f32 - 1000.0) / 1000.0)
+            .collect::<Vec<_>>();
+        assert_eq!(checksum(&samples), checksum(&samples.clone()));
+        let nudged = samples.iter().map(|s| s + 1e-7).collect::<Vec<_>>();
+        assert_eq!(checksum(&nudged), checksum(&samples));
+        let changed = samples.iter().map(|s| s + 1e-4).collect::<Vec<_>>();
+        assert_ne!(checksum(&changed), checksum(&samples));
+
+        let expectation = Expectation::Checksum(checksum(&samples));
+        assert_eq!(expectation.check(&samples, RATE), Ok(()));
+        assert!(expectation.check(&changed, RATE).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::ModuleId;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub match_case: bool,
+    /// only matches that are not part of a longer identifier
+    pub whole_word: bool,
+}
+
+/// A match of the search in one line of a module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hit {
+    pub module: ModuleId,
+    pub line: usize,
+    /// byte range of the match inside the line
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte length of `pattern` if `text` starts with it.
+fn match_at(text: &str, pattern: &str, match_case: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for p in pattern.chars() {
+        let (_, c) = chars.next()?;
+        let equal = match match_case {
+            true => c == p,
+            false => c.to_lowercase().eq(p.to_lowercase()),
+        };
+        if !equal {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+/// Byte ranges of the non-overlapping matches of `pattern` in `text`.
+pub fn matches(text: &str, pattern: &str, options: Options) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+    if pattern.is_empty() {
+        return matches;
+    }
+    let mut start = 0;
+    while let Some(c) = text[start..].chars().next() {
+        if let Some(len) = match_at(&text[start..], pattern, options.match_case) {
+            let end = start + len;
+            let bounded = !text[..start].chars().next_back().is_some_and(is_word)
+                && !text[end..].chars().next().is_some_and(is_word);
+            if !options.whole_word || bounded {
+                matches.push(start..end);
+                start = end;
+                continue;
+            }
+        }
+        start += c.len_utf8();
+    }
+    matches
+}
+
+/// Every match of `pattern` in the modules, sorted by module and line.
+pub fn find(modules: &HashMap<ModuleId, String>, pattern: &str, options: Options) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for (module, content) in modules {
+        for (line, text) in content.lines().enumerate() {
+            hits.extend(
+                matches(text, pattern, options)
+                    .into_iter()
+                    .map(|range| Hit {
+                        module: module.clone(),
+                        line,
+                        range,
+                        text: text.to_string(),
+                    }),
+            );
+        }
+    }
+    hits.sort_by(|a, b| {
+        (&a.module, a.line, a.range.start).cmp(&(&b.module, b.line, b.range.start))
+    });
+    hits
+}
+
+/// `text` with every match of `pattern` replaced.
+pub fn replace(text: &str, pattern: &str, replacement: &str, options: Options) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+    for range in matches(text, pattern, options) {
+        replaced.push_str(&text[last..range.start]);
+        replaced.push_str(replacement);
+        last = range.end;
+    }
+    replaced.push_str(&text[last..]);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHOLE_WORD: Options = Options {
+        match_case: false,
+        whole_word: true,
+    };
+    const MATCH_CASE: Options = Options {
+        match_case: true,
+        whole_word: false,
+    };
+
+    #[test]
+    fn matches_ignore_case_by_default() {
+        let text = "let Pitch = pitch * PITCH;";
+        assert_eq!(
+            matches(text, "pitch", Options::default()),
+            [4..9, 12..17, 20..25]
+        );
+        assert_eq!(
+            matches(text, "pitch", MATCH_CASE),
+            [Range { start: 12, end: 17 }]
+        );
+        assert_eq!(matches(text, "", Options::default()), []);
+    }
+
+    #[test]
+    fn whole_word_skips_parts_of_identifiers() {
+        let text = "pitch + pitch_env + base_pitch + pitch2 + (pitch)";
+        assert_eq!(matches(text, "pitch", WHOLE_WORD), [0..5, 43..48]);
+    }
+
+    #[test]
+    fn matches_do_not_overlap() {
+        assert_eq!(matches("aaaa", "aa", Options::default()), [0..2, 2..4]);
+    }
+
+    #[test]
+    fn matches_are_byte_ranges() {
+        let text = "// ÄÖ gain: gain";
+        assert_eq!(matches(text, "gain", Options::default()), [8..12, 14..18]);
+        assert_eq!(
+            matches("ÄÖ", "äö", Options::default()),
+            [Range { start: 0, end: 4 }]
+        );
+    }
+
+    #[test]
+    fn replace_every_match() {
+        let text = "let gain = gain_env * gain;";
+        assert_eq!(
+            replace(text, "gain", "level", WHOLE_WORD),
+            "let level = gain_env * level;"
+        );
+        assert_eq!(
+            replace(text, "GAIN", "x", Options::default()),
+            "let x = x_env * x;"
+        );
+        assert_eq!(replace(text, "gain", "gain", MATCH_CASE), text);
+    }
+
+    #[test]
+    fn find_sorts_hits_by_module_and_line() {
+        let modules = HashMap::from([
+            (ModuleId::new("b").unwrap(), "x\nx x\n".to_string()),
+            (ModuleId::new("a").unwrap(), "y\nx\n".to_string()),
+        ]);
+        let hits = find(&modules, "x", Options::default())
+            .into_iter()
+            .map(|h| (h.module.to_string(), h.line, h.range.start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hits,
+            [
+                ("a".into(), 1, 0),
+                ("b".into(), 0, 0),
+                ("b".into(), 1, 0),
+                ("b".into(), 1, 2)
+            ]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Saved and replaced snapshots kept per module, older ones are deleted.
+const HISTORY_LIMIT: usize = 100;
+/// Compiled snapshots kept per module, counted apart so compiles can't push out saves.
+const COMPILED_LIMIT: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Saved,
+    Compiled,
+    /// the buffer as it was before a snapshot was restored over it
+    Replaced,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Saved => "saved",
+            Kind::Compiled => "compiled",
+            Kind::Replaced => "replaced",
+        }
+    }
+
+    /// Snapshots of this kind kept per module.
+    fn limit(self) -> usize {
+        match self {
+            Kind::Compiled => COMPILED_LIMIT,
+            Kind::Saved | Kind::Replaced => HISTORY_LIMIT,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "saved" => Some(Kind::Saved),
+            "compiled" => Some(Kind::Compiled),
+            "replaced" => Some(Kind::Replaced),
+            _ => None,
+        }
+    }
+}
+
+/// A stored version of a module, named `<unix millis>-<kind>` inside the module's
+/// history folder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub millis: u64,
+    pub kind: Kind,
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    fn from_path(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (millis, kind) = name.split_once('-')?;
+        Some(Self {
+            millis: millis.parse().ok()?,
+            kind: Kind::from_name(kind)?,
+            path,
+        })
+    }
+
+    pub fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.path)
+    }
+
+    /// Age of the snapshot and its kind, like `5m ago, saved`.
+    pub fn label(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let age = now
+            .saturating_sub(Duration::from_millis(self.millis))
+            .as_secs();
+        let age = match age {
+            0..=59 => format!("{age}s"),
+            60..=3599 => format!("{}m", age / 60),
+            3600..=86_399 => format!("{}h", age / 3600),
+            _ => format!("{}d", age / 86_400),
+        };
+        format!("{age} ago, {}", self.kind.name())
+    }
+}
+
+/// Snapshots in the history folder `dir`, newest first.
+pub fn list(dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut snapshots = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| Snapshot::from_path(e.path()))
+        .collect::<Vec<_>>();
+    snapshots.sort_unstable_by_key(|s| std::cmp::Reverse(s.millis));
+    snapshots
+}
+
+/// Stores `content` as a new snapshot, unless it equals the newest one, and drops the
+/// oldest snapshots of its kind beyond the kind's limit.
+pub fn record(dir: &Path, content: &str, kind: Kind) -> io::Result<()> {
+    let snapshots = list(dir);
+    if let Some(newest) = snapshots.first() {
+        if newest.read().is_ok_and(|c| c == content) {
+            return Ok(());
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    // two snapshots within a millisecond would otherwise share a name
+    let millis = snapshots.first().map_or(now, |s| now.max(s.millis + 1));
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(format!("{millis}-{}", kind.name())), content)?;
+
+    let older = snapshots.iter().filter(|s| s.kind == kind);
+    for old in older.skip(kind.limit() - 1) {
+        let _ = fs::remove_file(&old.path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::temp_folder;
+    use super::*;
+
+    #[test]
+    fn record_skips_unchanged_content() {
+        let dir = temp_folder("history-record");
+        record(&dir, "a", Kind::Saved).unwrap();
+        record(&dir, "a", Kind::Compiled).unwrap();
+        record(&dir, "b", Kind::Compiled).unwrap();
+
+        let snapshots = list(&dir);
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].kind, Kind::Compiled);
+        assert_eq!(snapshots[0].read().unwrap(), "b");
+        assert_eq!(snapshots[1].kind, Kind::Saved);
+        assert_eq!(snapshots[1].read().unwrap(), "a");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compiles_do_not_push_out_saves() {
+        let dir = temp_folder("history-limit");
+        record(&dir, "saved", Kind::Saved).unwrap();
+        for i in 0..COMPILED_LIMIT + 5 {
+            record(&dir, &i.to_string(), Kind::Compiled).unwrap();
+        }
+
+        let snapshots = list(&dir);
+        let compiled = snapshots.iter().filter(|s| s.kind == Kind::Compiled);
+        assert_eq!(compiled.count(), COMPILED_LIMIT);
+        assert_eq!(
+            snapshots[0].read().unwrap(),
+            (COMPILED_LIMIT + 4).to_string()
+        );
+        assert_eq!(snapshots.last().unwrap().kind, Kind::Saved);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saves_are_limited() {
+        let dir = temp_folder("history-saves");
+        for i in 0..HISTORY_LIMIT + 2 {
+            record(&dir, &i.to_string(), Kind::Saved).unwrap();
+        }
+        let snapshots = list(&dir);
+        assert_eq!(snapshots.len(), HISTORY_LIMIT);
+        assert_eq!(snapshots.last().unwrap().read().unwrap(), "2");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Identifies a module by its path relative to the project's `modules` folder, so ids
+/// stay valid when the project is moved and non-UTF-8 file names are kept intact.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModuleId(PathBuf);
+
+impl ModuleId {
+    /// Creates an id from a relative path, rejecting empty, absolute and `..` paths.
+    pub fn new(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        is_valid_path(path).then(|| Self(path.to_path_buf()))
+    }
+
+    /// Creates the id of a file inside the modules folder `root`.
+    pub fn from_path(root: &Path, path: &Path) -> Option<Self> {
+        Self::new(path.strip_prefix(root).ok()?)
+    }
+
+    /// Location of the module inside the modules folder `root`.
+    pub fn path(&self, root: &Path) -> PathBuf {
+        root.join(&self.0)
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl fmt::Display for ModuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+/// Whether `path` is a non-empty relative path that stays inside the folder it is joined to.
+pub fn is_valid_path(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_accepts_relative_paths() {
+        assert!(ModuleId::new("bass").is_some());
+        assert!(ModuleId::new("drums/kick").is_some());
+    }
+
+    #[test]
+    fn new_rejects_paths_leaving_the_folder() {
+        assert_eq!(ModuleId::new(""), None);
+        assert_eq!(ModuleId::new("/etc/passwd"), None);
+        assert_eq!(ModuleId::new("../bass"), None);
+        assert_eq!(ModuleId::new("drums/../../bass"), None);
+        assert_eq!(ModuleId::new("./bass"), None);
+    }
+
+    #[test]
+    fn from_path_strips_the_root() {
+        let root = Path::new("/project/modules");
+        let id = ModuleId::from_path(root, Path::new("/project/modules/drums/kick")).unwrap();
+        assert_eq!(id.as_path(), Path::new("drums/kick"));
+        assert_eq!(id.path(root), root.join("drums/kick"));
+        assert_eq!(
+            ModuleId::from_path(root, Path::new("/elsewhere/kick")),
+            None
+        );
+        assert_eq!(ModuleId::from_path(root, root), None);
+    }
+}
@@ -0,0 +1,137 @@
+/// Information from the comment header at the top of a module:
+///
+/// ```text
+/// // @description short kick with a pitch drop
+/// // @author bruno
+/// // @tags drums, kick
+/// // @bpm 120
+/// // @key C#m
+/// // @length 0.5
+/// // @expect peak below 0.9
+/// ```
+///
+/// `@length` is the preferred render length in seconds, each `@expect` is a check on the
+/// render, see [`super::expect::Expectation`]. Unknown fields and values that fail to
+/// parse are ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    pub bpm: Option<f64>,
+    pub key: Option<String>,
+    pub length: Option<f64>,
+    pub expect: Vec<String>,
+}
+
+impl Metadata {
+    /// Reads the header, which ends at the first line that is neither empty nor a comment.
+    pub fn parse(source: &str) -> Self {
+        let mut metadata = Self::default();
+        for line in source.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            let Some(comment) = line.strip_prefix("//") else {
+                break;
+            };
+            let Some((field, value)) = comment.trim().strip_prefix('@').map(|f| {
+                let (field, value) = f.split_once(char::is_whitespace).unwrap_or((f, ""));
+                (field, value.trim())
+            }) else {
+                continue;
+            };
+            if value.is_empty() {
+                continue;
+            }
+
+            match field {
+                "description" => metadata.description = Some(value.to_string()),
+                "author" => metadata.author = Some(value.to_string()),
+                "tags" => metadata.tags.extend(
+                    value
+                        .split(',')
+                        .map(|t| t.trim().to_lowercase())
+                        .filter(|t| !t.is_empty()),
+                ),
+                "bpm" => metadata.bpm = value.parse().ok().filter(|b: &f64| *b > 0.0),
+                "key" => metadata.key = Some(value.to_string()),
+                "length" => metadata.length = value.parse().ok().filter(|l: &f64| *l > 0.0),
+                "expect" => metadata.expect.push(value.to_string()),
+                _ => (),
+            }
+        }
+        metadata
+    }
+
+    /// Lines shown in the module info panel.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(description) = &self.description {
+            lines.push(description.clone());
+        }
+        if let Some(author) = &self.author {
+            lines.push(format!("by {author}"));
+        }
+        if !self.tags.is_empty() {
+            lines.push(format!("tags: {}", self.tags.join(", ")));
+        }
+        match (self.bpm, &self.key) {
+            (Some(bpm), Some(key)) => lines.push(format!("{bpm} bpm in {key}")),
+            (Some(bpm), None) => lines.push(format!("{bpm} bpm")),
+            (None, Some(key)) => lines.push(format!("key: {key}")),
+            (None, None) => (),
+        }
+        if let Some(length) = self.length {
+            lines.push(format!("length: {length}s"));
+        }
+        if !self.expect.is_empty() {
+            lines.push(format!("{} expectations", self.expect.len()));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_field() {
+        let source = "// @description short kick\n\
+                      // @author bruno\n\
+                      // @tags Drums, kick,\n\
+                      // @bpm 120\n\
+                      // @key C#m\n\
+                      // @length 0.5\n\
+                      // @expect peak below 0.9\n\
+                      // @expect silent after 0.4\n\
+                      fn main(input: Num) -> Num {}\n";
+        assert_eq!(
+            Metadata::parse(source),
+            Metadata {
+                description: Some("short kick".into()),
+                author: Some("bruno".into()),
+                tags: vec!["drums".into(), "kick".into()],
+                bpm: Some(120.0),
+                key: Some("C#m".into()),
+                length: Some(0.5),
+                expect: vec!["peak below 0.9".into(), "silent after 0.4".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn header_ends_at_the_first_code_line() {
+        let source = "\n// plain comment\n// @author bruno\n\nfn main() {}\n// @key C\n";
+        let metadata = Metadata::parse(source);
+        assert_eq!(metadata.author.as_deref(), Some("bruno"));
+        assert_eq!(metadata.key, None);
+    }
+
+    #[test]
+    fn ignores_bad_values_and_unknown_fields() {
+        let source = "// @bpm fast\n// @length -1\n// @color red\n// @author\n";
+        assert_eq!(Metadata::parse(source), Metadata::default());
+    }
+}
@@ -0,0 +1,2359 @@
+mod diff;
+mod expect;
+mod find;
+mod history;
+mod id;
+mod metadata;
+mod profile;
+pub mod render;
+mod search;
+mod tab;
+mod templates;
+mod undo;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::audio;
+use crate::diagnostic::Diagnostic;
+use crate::format;
+use crate::highlighter::{self, Highlighter};
+use crate::widgets::{self, menu, tree, Tree};
+use crate::{graph, widgets::Menu, Message};
+use iced::widget;
+use iced::widget::{
+    text_editor::{Action, Content, Edit},
+    TextEditor,
+};
+use iced::Element;
+// use crate::Element;
+
+pub use id::ModuleId;
+pub use metadata::Metadata;
+pub use profile::{Profile, Profiling};
+use render::Render;
+use tab::{Finished, Tab};
+
+#[derive(Clone, Debug)]
+pub enum ModuleMessage {
+    Save,
+    SelectModule(ModuleId),
+    RemoveModule(ModuleId),
+    RenameModule(ModuleId),
+    DuplicateModule(ModuleId),
+    AddFolder,
+    Tree(tree::Event<ModuleId>),
+    ToggleTree(bool),
+    Search(String),
+    SearchContents(bool),
+    MoveCursor(isize),
+    SubmitCursor,
+    FilterTag(String),
+    ClearTagFilter,
+    ToggleFavourite(ModuleId),
+    FavouritesOnly(bool),
+    Rescan,
+    Editor(Action),
+    /// action of the editor in the right pane of the split view
+    SplitEditor(Action),
+    CloseTab,
+    ToggleSplit,
+    SwapSplit,
+    Undo,
+    Redo,
+    /// completes the word at the cursor with the item, `None` takes the first suggestion
+    Complete(Option<String>),
+    AddModule,
+    AddModuleInput(String),
+    SelectTemplate(Option<String>),
+    CompileModule,
+    TestModule,
+    ProfileModule,
+    Stop,
+    GotoDiagnostic(Diagnostic),
+    ToggleLive(bool),
+    Format,
+    FormatOnSave(bool),
+    ToggleHistory(bool),
+    SelectSnapshot(usize),
+    RestoreSnapshot,
+    ToggleFind(bool),
+    FindInput(String),
+    ReplaceInput(String),
+    FindOptions(find::Options),
+    /// opens the module with the cursor at the line and byte column of a hit
+    FindGoto(ModuleId, usize, usize),
+    PreviewReplace,
+    ReplaceAll,
+}
+
+/// Id of the add module input, focused by the new module shortcut.
+pub const ADD_MODULE_INPUT: &str = "add-module";
+/// Id of the search input, focused by the quick open shortcut.
+pub const SEARCH_INPUT: &str = "search";
+/// Id of the input of the find panel, focused by the find shortcut.
+pub const FIND_INPUT: &str = "find";
+/// Hits listed in the find panel, the count above the list stays exact.
+const HIT_LIMIT: usize = 500;
+
+/// Pause after the last edit before live mode recompiles.
+const LIVE_DELAY: Duration = Duration::from_millis(400);
+/// How often unsaved buffers are written to the recovery folder.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// How often the modules folder is checked for changes made by other programs.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// Samples per second rendered by TEST.
+pub const TEST_RATE: usize = 48_000;
+/// Longest a short render may run before it is aborted.
+const RENDER_BUDGET: Duration = Duration::from_secs(5);
+/// How many times slower than playback a long render may run before it is aborted.
+const SLOWEST: f64 = 10.0;
+/// Points of the graph shown after a compile, and the input step between them.
+const PREVIEW_SAMPLES: usize = 100;
+const PREVIEW_SCALE: f64 = 0.01;
+/// Input step between the samples TEST plays.
+const TEST_SCALE: f64 = 0.0001;
+
+/// Snapshots of a module being browsed, with the render and diff of the selected one.
+struct HistoryView {
+    module: ModuleId,
+    snapshots: Vec<history::Snapshot>,
+    selected: Option<usize>,
+    content: String,
+    preview: Result<Vec<f32>, Diagnostic>,
+    /// render of the selected snapshot while it runs
+    render: Option<Render>,
+    /// changes from the selected snapshot to the current buffer
+    diff: Vec<diff::Line>,
+}
+
+/// Replace of one module as previewed, applied only while the module still has `old`.
+struct Replacement {
+    module: ModuleId,
+    old: String,
+    new: String,
+    /// changed lines, shown in the find panel
+    lines: Vec<diff::Line>,
+}
+
+pub struct Modules {
+    path: PathBuf,
+    /// open modules, the selected one is shown in the left pane
+    tabs: Vec<Tab>,
+    tab_bar: Menu<Message, ModuleId>,
+    /// module shown in the right pane of the split view
+    split: Option<ModuleId>,
+    /// shown by the editor while no module is open
+    blank: Content,
+    module_add_text: String,
+    /// names of the files in the templates folder
+    templates: Vec<String>,
+    /// modification time of the templates folder, which changes as files come and go
+    templates_modified: Option<SystemTime>,
+    /// template new modules are created from, `None` creates an empty file
+    template: Option<String>,
+    modules: HashMap<ModuleId, String>,
+    /// module contents as last read from or written to disk
+    saved: HashMap<ModuleId, String>,
+    /// parsed headers, dropped when the module changes and parsed again by `refresh_files`
+    metadata: HashMap<ModuleId, Metadata>,
+    /// modification times of the module files, only files whose time changed are read
+    mtimes: HashMap<ModuleId, SystemTime>,
+    /// undo and redo stacks, kept while other modules are open
+    undo: HashMap<ModuleId, undo::Undo>,
+    last_autosave: Instant,
+    /// errors of the last file operation, shown below the file list buttons
+    file_errors: Vec<String>,
+    /// modules changed on disk while their buffer had unsaved edits
+    conflicts: HashSet<ModuleId>,
+    files: Menu<Message, ModuleId>,
+    tree: Tree<Message, ModuleId>,
+    /// folders below the modules folder, read again on rescans and folder changes
+    folders: Vec<PathBuf>,
+    /// shows the modules as a folder tree instead of a flat list
+    tree_view: bool,
+    search: String,
+    /// also matches the search against module contents
+    search_contents: bool,
+    /// only lists modules with this `@tags` entry
+    tag_filter: Option<String>,
+    favourites: HashSet<ModuleId>,
+    favourites_only: bool,
+    live: bool,
+    format_on_save: bool,
+    /// shown instead of the render while browsing a module's snapshots
+    history: Option<HistoryView>,
+    /// shows the project wide find and replace panel
+    find_open: bool,
+    find_text: String,
+    replace_text: String,
+    find_options: find::Options,
+    hits: Vec<find::Hit>,
+    /// result of replacing in each module, shown before it is applied and dropped when
+    /// a module changes
+    replace_preview: Option<Vec<Replacement>>,
+}
+impl Modules {
+    pub fn new(path: PathBuf) -> Self {
+        let path = path.join(PathBuf::from("modules"));
+        let _ = fs::create_dir_all(&path);
+        let path = path.canonicalize().unwrap();
+        let mut modules = Self {
+            tabs: Vec::new(),
+            tab_bar: Menu::new(
+                Vec::new(),
+                |module| Message::Editor(ModuleMessage::SelectModule(module)),
+                menu::Flow::Horizontal,
+            ),
+            split: None,
+            blank: Content::new(),
+            modules: HashMap::default(),
+            saved: HashMap::default(),
+            metadata: HashMap::default(),
+            mtimes: HashMap::default(),
+            undo: HashMap::default(),
+            last_autosave: Instant::now(),
+            file_errors: Vec::new(),
+            conflicts: HashSet::new(),
+            module_add_text: String::new(),
+            templates: Vec::new(),
+            templates_modified: None,
+            template: None,
+            path,
+            files: Menu::new(
+                Vec::new(),
+                |module| Message::Editor(ModuleMessage::SelectModule(module)),
+                menu::Flow::Vertical,
+            ),
+            tree: Tree::new("modules", |event| {
+                Message::Editor(ModuleMessage::Tree(event))
+            }),
+            folders: Vec::new(),
+            tree_view: true,
+            search: String::new(),
+            search_contents: false,
+            tag_filter: None,
+            favourites: HashSet::new(),
+            favourites_only: false,
+            live: false,
+            format_on_save: false,
+            history: None,
+            find_open: false,
+            find_text: String::new(),
+            replace_text: String::new(),
+            find_options: find::Options::default(),
+            hits: Vec::new(),
+            replace_preview: None,
+        };
+        modules.load_modules().unwrap();
+        modules.saved = modules.modules.clone();
+        modules.restore_recovery();
+        modules.load_favourites();
+        modules.templates = templates::list(&modules.templates_path());
+        modules.templates_modified = modules.templates_modified();
+        if modules.templates.iter().any(|t| t == "main") {
+            modules.template = Some("main".into());
+        }
+        modules.refresh_files();
+        modules
+    }
+
+    /// Writes the editor content of every tab back into its module.
+    fn store_content(&mut self) {
+        for tab in &self.tabs {
+            let Some(module) = self.modules.get_mut(&tab.module) else {
+                continue;
+            };
+            let text = tab.content.text();
+            if *module != text {
+                *module = text;
+                self.metadata.remove(&tab.module);
+                self.replace_preview = None;
+            }
+        }
+    }
+
+    fn tab(&self, module: &ModuleId) -> Option<&Tab> {
+        self.tabs.iter().find(|t| &t.module == module)
+    }
+
+    /// Tab of the selected module.
+    fn active(&self) -> Option<&Tab> {
+        self.tab(self.files.selected().as_ref()?)
+    }
+
+    fn active_mut(&mut self) -> Option<&mut Tab> {
+        let module = self.files.selected().clone()?;
+        self.tabs.iter_mut().find(|t| t.module == module)
+    }
+
+    /// Shows the module in the left pane, opening a tab for it if needed. Opening the
+    /// module of the right pane swaps the panes.
+    fn open(&mut self, module: ModuleId) {
+        self.store_content();
+        if self.tab(&module).is_none() {
+            let Some(content) = self.modules.get(&module) else {
+                return;
+            };
+            self.tabs.push(Tab::new(module.clone(), content));
+        }
+        if self.split.as_ref() == Some(&module) {
+            self.split = self.files.selected().clone();
+        }
+        if let Some(folder) = module.as_path().parent() {
+            self.tree.expand(folder);
+        }
+        self.files.select(module.clone());
+        self.tab_bar.select(module);
+        self.history = None;
+        self.refresh_files();
+    }
+
+    /// Closes the module's tab, showing a neighbouring tab if it was the selected one.
+    fn close_tab(&mut self, module: &ModuleId) {
+        self.store_content();
+        let Some(index) = self.tabs.iter().position(|t| &t.module == module) else {
+            return;
+        };
+        self.tabs.remove(index);
+        if self.split.as_ref() == Some(module) {
+            self.split = None;
+        }
+        if self.files.selected().as_ref() != Some(module) {
+            return self.refresh_files();
+        }
+
+        self.history = None;
+        let next = self.tabs.get(index).or(self.tabs.last());
+        match next.map(|t| t.module.clone()) {
+            Some(next) => self.open(next),
+            None => {
+                self.files.deselect();
+                self.tab_bar.deselect();
+                self.refresh_files();
+            }
+        }
+    }
+
+    /// Applies an editor action to the module's tab, recording edits for undo.
+    fn edit(&mut self, module: &ModuleId, action: Action) {
+        let Some(tab) = self.tabs.iter_mut().find(|t| &t.module == module) else {
+            return;
+        };
+        let undo = self.undo.entry(module.clone()).or_default();
+        match &action {
+            Action::Edit(e) => undo.edit(e, || tab.state()),
+            Action::Scroll { .. } => (),
+            _ => undo.break_group(),
+        }
+
+        let edit = action.is_edit();
+        tab.content.perform(action);
+        if edit {
+            tab.last_edit = Some(Instant::now());
+            self.store_content();
+            self.refresh_files();
+        }
+    }
+
+    /// Steps the selected module back or, with `redo`, forward through its undo history.
+    fn undo(&mut self, redo: bool) {
+        let Some(module) = self.files.selected().clone() else {
+            return;
+        };
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.module == module) else {
+            return;
+        };
+        let undo = self.undo.entry(module).or_default();
+        let state = match redo {
+            true => undo.redo(tab.state()),
+            false => undo.undo(tab.state()),
+        };
+        let Some(state) = state else {
+            return;
+        };
+
+        tab.set_state(&state);
+        self.store_content();
+        self.refresh_files();
+    }
+
+    /// Types the rest of a completion of the word at the cursor of the selected module.
+    fn complete(&mut self, label: Option<String>) {
+        let Some(module) = self.files.selected().clone() else {
+            return;
+        };
+        let Some(tab) = self.tab(&module) else {
+            return;
+        };
+        let (prefix, items) = tab.completions();
+        let Some(item) = items
+            .into_iter()
+            .find(|i| label.is_none() || label.as_ref() == Some(&i.label))
+        else {
+            return;
+        };
+        for c in item.label[prefix.len()..].chars() {
+            self.edit(&module, Action::Edit(Edit::Insert(c)));
+        }
+    }
+
+    /// Pretty-prints the module, as one undo step of its buffer.
+    fn format_module(&mut self, module: &ModuleId) -> Result<(), String> {
+        self.store_content();
+        let Some(old) = self.modules.get(module) else {
+            return Ok(());
+        };
+        let new = format::format(old)?;
+        if new == *old {
+            return Ok(());
+        }
+
+        let undo = self.undo.entry(module.clone()).or_default();
+        match self.tabs.iter_mut().find(|t| &t.module == module) {
+            Some(tab) => {
+                let before = tab.state();
+                let cursor = before.cursor;
+                undo.push(before);
+                tab.set_state(&undo::State {
+                    text: new.clone(),
+                    cursor,
+                });
+            }
+            None => undo.push(undo::State {
+                text: old.clone(),
+                cursor: (0, 0),
+            }),
+        }
+        self.modules.insert(module.clone(), new);
+        self.metadata.remove(module);
+        self.refresh_files();
+        Ok(())
+    }
+
+    /// Whether the module differs from its content on disk.
+    fn is_dirty(&self, module: &ModuleId) -> bool {
+        match (self.modules.get(module), self.saved.get(module)) {
+            // the editor always ends the text with a newline
+            (Some(content), Some(saved)) => {
+                content.trim_end_matches('\n') != saved.trim_end_matches('\n')
+            }
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Hidden folder in the project root holding recovery files and the trash.
+    fn data_path(&self) -> PathBuf {
+        self.path.parent().unwrap_or(&self.path).join(".mksnd")
+    }
+
+    fn recovery_path(&self) -> PathBuf {
+        self.data_path().join("recovery")
+    }
+
+    fn recovery_file(&self, module: &ModuleId) -> PathBuf {
+        module.path(&self.recovery_path())
+    }
+
+    /// Writes unsaved buffers to the recovery folder and drops recovery files of
+    /// modules that are clean again.
+    pub fn write_recovery(&mut self) {
+        self.store_content();
+        for module in self.modules.keys() {
+            let path = self.recovery_file(module);
+            if self.is_dirty(module) {
+                let _ = fs::create_dir_all(path.parent().unwrap());
+                let _ = fs::write(&path, &self.modules[module]);
+            } else {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+
+    /// Loads buffers left in the recovery folder by a previous session.
+    fn restore_recovery(&mut self) {
+        let modules = self.modules.keys().cloned().collect::<Vec<_>>();
+        for module in modules {
+            if let Ok(content) = fs::read_to_string(self.recovery_file(&module)) {
+                self.modules.insert(module, content);
+            }
+        }
+    }
+
+    /// Folder holding the snapshots of a module.
+    fn history_path(&self, module: &ModuleId) -> PathBuf {
+        module.path(&self.data_path().join("history"))
+    }
+
+    /// Snapshots the source the tab compiled successfully.
+    fn record_compiled(&self, tab: &Tab) {
+        let _ = history::record(
+            &self.history_path(&tab.module),
+            &tab.source,
+            history::Kind::Compiled,
+        );
+    }
+
+    fn select_snapshot(&mut self, index: usize) {
+        let current = self
+            .history
+            .as_ref()
+            .and_then(|h| self.tab(&h.module))
+            .map(|t| t.content.text())
+            .unwrap_or_default();
+        let Some(history) = &mut self.history else {
+            return;
+        };
+        let Some(snapshot) = history.snapshots.get(index) else {
+            return;
+        };
+        let content = match snapshot.read() {
+            Ok(content) => content,
+            Err(e) => {
+                return self
+                    .file_errors
+                    .push(format!("history {}: {e}", history.module))
+            }
+        };
+
+        history.render = None;
+        match Render::start(
+            &content,
+            "main",
+            0..PREVIEW_SAMPLES,
+            PREVIEW_SCALE,
+            RENDER_BUDGET,
+        ) {
+            Ok(render) => {
+                history.preview = Err(Diagnostic::new("rendering"));
+                history.render = Some(render);
+            }
+            Err(diagnostic) => history.preview = Err(diagnostic),
+        }
+        history.diff = diff::diff(&content, &current);
+        history.content = content;
+        history.selected = Some(index);
+    }
+
+    /// Replaces the buffer with the selected snapshot, snapshotting the buffer first.
+    fn restore_snapshot(&mut self) {
+        let Some(history) = self.history.take() else {
+            return;
+        };
+        if history.selected.is_none() {
+            return;
+        }
+        let path = self.history_path(&history.module);
+        let Some(tab) = self.tabs.iter_mut().find(|t| t.module == history.module) else {
+            return;
+        };
+        let before = tab.state();
+        let _ = history::record(&path, &before.text, history::Kind::Replaced);
+        let restored = undo::State {
+            text: history.content,
+            cursor: before.cursor,
+        };
+        self.undo.entry(history.module).or_default().push(before);
+        tab.set_state(&restored);
+        self.store_content();
+        self.refresh_files();
+    }
+
+    fn preview_replace(&mut self) {
+        self.store_content();
+        let mut modules = self.modules.keys().cloned().collect::<Vec<_>>();
+        modules.sort_unstable();
+
+        let mut preview = Vec::new();
+        for module in modules {
+            let old = self.modules[&module].clone();
+            let new = find::replace(&old, &self.find_text, &self.replace_text, self.find_options);
+            if new != old {
+                let mut lines = diff::diff(&old, &new);
+                lines.retain(|l| !matches!(l, diff::Line::Same(_)));
+                preview.push(Replacement {
+                    module,
+                    old,
+                    new,
+                    lines,
+                });
+            }
+        }
+        self.replace_preview = Some(preview);
+    }
+
+    /// Applies the previewed replace, skipping modules that changed since. The text before
+    /// the replace becomes an undo step of each changed module, saving is left to the user.
+    fn replace_all(&mut self) {
+        self.store_content();
+        let Some(preview) = self.replace_preview.take() else {
+            return;
+        };
+        for Replacement {
+            module, old, new, ..
+        } in preview
+        {
+            if self.modules.get(&module) != Some(&old) {
+                self.file_errors.push(format!(
+                    "replace: {module} changed since the preview and was left as is"
+                ));
+                continue;
+            }
+            let undo = self.undo.entry(module.clone()).or_default();
+            match self.tabs.iter_mut().find(|t| t.module == module) {
+                Some(tab) => {
+                    let before = tab.state();
+                    let cursor = before.cursor;
+                    undo.push(before);
+                    tab.set_state(&undo::State {
+                        text: new.clone(),
+                        cursor,
+                    });
+                }
+                None => undo.push(undo::State {
+                    text: old,
+                    cursor: (0, 0),
+                }),
+            }
+            self.metadata.remove(&module);
+            self.modules.insert(module, new);
+        }
+        self.refresh_files();
+    }
+
+    /// Folder in the project root holding the templates for new modules.
+    fn templates_path(&self) -> PathBuf {
+        self.path.parent().unwrap_or(&self.path).join("templates")
+    }
+
+    fn templates_modified(&self) -> Option<SystemTime> {
+        fs::metadata(self.templates_path())
+            .and_then(|m| m.modified())
+            .ok()
+    }
+
+    /// Writes the default templates to the templates folder of a new project.
+    pub fn seed_templates(&mut self) {
+        if let Err(e) = templates::seed(&self.templates_path()) {
+            self.file_errors.push(format!("templates: {e}"));
+        }
+        self.templates = templates::list(&self.templates_path());
+    }
+
+    fn favourites_file(&self) -> PathBuf {
+        self.data_path().join("favourites")
+    }
+
+    fn load_favourites(&mut self) {
+        let Ok(favourites) = fs::read_to_string(self.favourites_file()) else {
+            return;
+        };
+        self.favourites = favourites.lines().filter_map(ModuleId::new).collect();
+    }
+
+    /// Writes the favourites, one module path per line.
+    /// Writes the favourites one per line. Names that are not UTF-8 or hold a line
+    /// break can't be written that way and are left out, a lossy copy would not match
+    /// the module when read.
+    fn save_favourites(&mut self) {
+        let mut favourites = self
+            .favourites
+            .iter()
+            .filter_map(|m| m.as_path().to_str())
+            .filter(|m| !m.contains('\n'))
+            .collect::<Vec<_>>();
+        favourites.sort_unstable();
+        if let Err(e) = write_atomic(&self.favourites_file(), &favourites.join("\n")) {
+            self.file_errors.push(format!("favourites: {e}"));
+        }
+    }
+
+    /// Id of a new module named by the user, relative to the modules folder.
+    fn new_module_id(&self, name: impl AsRef<Path>) -> io::Result<ModuleId> {
+        let Some(module) = ModuleId::new(name) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid module name",
+            ));
+        };
+        if self.modules.contains_key(&module) || module.path(&self.path).exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "module already exists",
+            ));
+        }
+        Ok(module)
+    }
+
+    /// Moves the module into the trash folder, keeping its latest buffer.
+    fn remove_module(&mut self, module: &ModuleId) -> io::Result<()> {
+        self.store_content();
+        let stamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let trash = self.data_path().join("trash").join(stamp.to_string());
+
+        let content = self.modules.get(module).ok_or_else(|| not_loaded(module))?;
+        write_atomic(&module.path(&trash), content)?;
+        match fs::remove_file(module.path(&self.path)) {
+            // deleted by another program, only the buffer is left to remove
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            result => result?,
+        }
+        let _ = fs::remove_file(self.recovery_file(module));
+
+        self.modules.remove(module);
+        self.saved.remove(module);
+        self.mtimes.remove(module);
+        self.conflicts.remove(module);
+        self.undo.remove(module);
+        if self.favourites.remove(module) {
+            self.save_favourites();
+        }
+        self.close_tab(module);
+        Ok(())
+    }
+
+    /// Renames or moves the module to `name`, relative to the modules folder.
+    fn rename_module(&mut self, module: &ModuleId, name: impl AsRef<Path>) -> io::Result<()> {
+        self.store_content();
+        let new = self.new_module_id(name)?;
+        let path = new.path(&self.path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::rename(module.path(&self.path), &path)?;
+        self.refresh_folders();
+        let _ = fs::remove_file(self.recovery_file(module));
+        let history = self.history_path(&new);
+        if let Some(dir) = history.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::rename(self.history_path(module), history);
+
+        if let Some(content) = self.modules.remove(module) {
+            self.modules.insert(new.clone(), content);
+        }
+        if let Some(saved) = self.saved.remove(module) {
+            self.saved.insert(new.clone(), saved);
+        }
+        if let Some(undo) = self.undo.remove(module) {
+            self.undo.insert(new.clone(), undo);
+        }
+        if self.favourites.remove(module) {
+            self.favourites.insert(new.clone());
+            self.save_favourites();
+        }
+        if let Some(tab) = self.tabs.iter_mut().find(|t| &t.module == module) {
+            tab.module = new.clone();
+        }
+        if self.split.as_ref() == Some(module) {
+            self.split = Some(new.clone());
+        }
+        if self.files.selected().as_ref() == Some(module) {
+            self.files.select(new.clone());
+            self.tab_bar.select(new);
+        }
+        Ok(())
+    }
+
+    /// Moves the module into `folder`, keeping its file name.
+    fn move_module(&mut self, module: &ModuleId, folder: &Path) -> io::Result<()> {
+        if module.as_path().parent() == Some(folder) {
+            return Ok(());
+        }
+        let name = module.as_path().file_name().unwrap_or_default();
+        self.rename_module(module, folder.join(name))?;
+        self.tree.expand(folder);
+        Ok(())
+    }
+
+    /// Copies the module's current buffer to `name` and opens the copy.
+    fn duplicate_module(&mut self, module: &ModuleId, name: &str) -> io::Result<()> {
+        self.store_content();
+        let new = self.new_module_id(name)?;
+        let content = self
+            .modules
+            .get(module)
+            .ok_or_else(|| not_loaded(module))?
+            .clone();
+        write_atomic(&new.path(&self.path), &content)?;
+        self.refresh_folders();
+
+        self.modules.insert(new.clone(), content.clone());
+        self.saved.insert(new.clone(), content);
+        self.open(new);
+        Ok(())
+    }
+
+    /// Writes every modified module to disk, collecting the files that failed in
+    /// `file_errors` instead of aborting.
+    pub fn save_modules(&mut self) {
+        self.store_content();
+        self.file_errors.clear();
+
+        let mut modules = self
+            .modules
+            .keys()
+            .filter(|m| self.is_dirty(m))
+            .cloned()
+            .collect::<Vec<_>>();
+        modules.sort_unstable();
+
+        for module in modules {
+            // a module that fails to format is still saved as written
+            if self.format_on_save {
+                if let Err(e) = self.format_module(&module) {
+                    self.file_errors.push(format!("format {module}: {e}"));
+                }
+            }
+            let content = self.modules[&module].clone();
+            match write_atomic(&module.path(&self.path), &content) {
+                Ok(()) => {
+                    let _ = fs::remove_file(self.recovery_file(&module));
+                    let _ = history::record(
+                        &self.history_path(&module),
+                        &content,
+                        history::Kind::Saved,
+                    );
+                    self.conflicts.remove(&module);
+                    self.saved.insert(module, content);
+                }
+                Err(e) => self.file_errors.push(format!("{module}: {e}")),
+            }
+        }
+        self.refresh_files();
+    }
+
+    /// Marks modules with unsaved edits or conflicting changes on disk.
+    fn marker(&self, module: &ModuleId) -> &'static str {
+        if self.conflicts.contains(module) {
+            " !"
+        } else if self.is_dirty(module) {
+            " *"
+        } else {
+            ""
+        }
+    }
+
+    fn metadata(&self, module: &ModuleId) -> Metadata {
+        self.metadata.get(module).cloned().unwrap_or_default()
+    }
+
+    /// Whether the module passes the tag and favourites filters.
+    fn is_shown(&self, module: &ModuleId) -> bool {
+        if self.favourites_only && !self.favourites.contains(module) {
+            return false;
+        }
+        match &self.tag_filter {
+            Some(tag) => self.metadata(module).tags.contains(tag),
+            None => true,
+        }
+    }
+
+    /// Name of the module in the browser, with its favourite and file state markers.
+    fn label(&self, module: &ModuleId, name: &str) -> String {
+        let favourite = if self.favourites.contains(module) {
+            "★ "
+        } else {
+            ""
+        };
+        format!("{favourite}{name}{}", self.marker(module))
+    }
+
+    /// Reads the folders below the modules folder again.
+    fn refresh_folders(&mut self) {
+        self.folders.clear();
+        read_folders(&self.path, Path::new(""), &mut self.folders);
+    }
+
+    /// Rebuilds the flat list, the tree and the tab bar from the modules and the folders.
+    fn refresh_files(&mut self) {
+        self.metadata.retain(|m, _| self.modules.contains_key(m));
+        for (module, content) in &self.modules {
+            if !self.metadata.contains_key(module) {
+                self.metadata
+                    .insert(module.clone(), Metadata::parse(content));
+            }
+        }
+
+        let mut modules = self.modules.keys().cloned().collect::<Vec<_>>();
+        modules.retain(|m| self.is_shown(m));
+        modules.sort_unstable();
+
+        let leaves = modules
+            .iter()
+            .map(|m| {
+                let name = m.as_path().file_name().unwrap_or_default();
+                tree::Element {
+                    data: m.clone(),
+                    path: m.as_path().to_path_buf(),
+                    text: self.label(m, &name.to_string_lossy()),
+                }
+            })
+            .collect();
+        self.tree.set_elements(leaves, self.folders.clone());
+        self.files.set_elements(self.get_file_elements(modules));
+
+        let tabs = self
+            .tabs
+            .iter()
+            .map(|t| {
+                let name = t.module.as_path().file_name().unwrap_or_default();
+                widgets::menu::Element {
+                    data: t.module.clone(),
+                    text: format!("{}{}", name.to_string_lossy(), self.marker(&t.module)),
+                }
+            })
+            .collect();
+        self.tab_bar.set_elements(tabs);
+
+        if self.find_open {
+            self.hits = find::find(&self.modules, &self.find_text, self.find_options);
+        }
+    }
+
+    /// Menu elements of the modules matching the search, best match first.
+    fn get_file_elements(
+        &self,
+        mut modules: Vec<ModuleId>,
+    ) -> Vec<widgets::menu::Element<ModuleId>> {
+        if !self.search.is_empty() {
+            let mut scored = modules
+                .into_iter()
+                .filter_map(|m| {
+                    let score =
+                        search::fuzzy_score(&self.search, &m.to_string()).or_else(|| {
+                            (self.search_contents
+                                && search::contains(&self.modules[&m], &self.search))
+                            .then_some(0)
+                        })?;
+                    Some((score, m))
+                })
+                .collect::<Vec<_>>();
+            // stable, so equal scores stay sorted by name
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            modules = scored.into_iter().map(|(_, m)| m).collect();
+        }
+
+        modules
+            .iter()
+            .map(|o| widgets::menu::Element {
+                data: o.clone(),
+                text: self.label(o, &o.to_string()),
+            })
+            .collect()
+    }
+
+    /// Whether the flat list is shown instead of the tree, search results are always flat.
+    fn list_view(&self) -> bool {
+        !self.tree_view || !self.search.is_empty()
+    }
+
+    pub fn load_modules(&mut self) -> io::Result<()> {
+        let dir = match fs::read_dir(&self.path) {
+            Ok(dir) => dir,
+            Err(_) => {
+                fs::create_dir_all(&self.path)?;
+                return Ok(());
+            }
+        };
+        read_dir(&self.path, dir, &mut self.modules);
+        if let Ok(dir) = fs::read_dir(&self.path) {
+            self.folders.clear();
+            read_mtimes(&self.path, dir, &mut self.mtimes, &mut self.folders);
+        }
+
+        Ok(())
+    }
+
+    /// Merges modules added, removed or changed by other programs into the open
+    /// project. Modules with unsaved edits keep their buffer and are flagged instead.
+    fn rescan(&mut self) {
+        let Ok(dir) = fs::read_dir(&self.path) else {
+            return;
+        };
+        let mut mtimes = HashMap::new();
+        let mut folders = Vec::new();
+        read_mtimes(&self.path, dir, &mut mtimes, &mut folders);
+        let templates_modified = self.templates_modified();
+        // this runs every second, mostly without anything changed on disk. Conflicts are
+        // checked each time, they go away once the buffer matches the file again.
+        if mtimes == self.mtimes
+            && folders == self.folders
+            && templates_modified == self.templates_modified
+            && self.conflicts.is_empty()
+        {
+            return;
+        }
+        self.store_content();
+
+        let known = self.saved.keys().cloned().collect::<Vec<_>>();
+        for module in known {
+            if mtimes.contains_key(&module) {
+                continue;
+            }
+            self.replace_preview = None;
+            if self.is_dirty(&module) {
+                self.saved.remove(&module);
+                self.conflict(module, "deleted");
+            } else {
+                self.saved.remove(&module);
+                self.modules.remove(&module);
+                self.close_tab(&module);
+            }
+        }
+
+        let changed = mtimes
+            .iter()
+            .filter(|(module, time)| self.mtimes.get(*module) != Some(*time))
+            .filter_map(|(module, _)| {
+                let content = fs::read_to_string(module.path(&self.path)).ok()?;
+                Some((module.clone(), content))
+            })
+            .collect::<Vec<_>>();
+        self.mtimes = mtimes;
+
+        for (module, content) in changed {
+            if self.saved.get(&module) == Some(&content) {
+                continue;
+            }
+            if self.is_dirty(&module) && self.modules.get(&module) != Some(&content) {
+                self.conflict(module.clone(), "changed");
+            } else {
+                if let Some(tab) = self.tabs.iter_mut().find(|t| t.module == module) {
+                    let before = tab.state();
+                    let cursor = before.cursor;
+                    self.undo.entry(module.clone()).or_default().push(before);
+                    tab.set_state(&undo::State {
+                        text: content.clone(),
+                        cursor,
+                    });
+                }
+                self.modules.insert(module.clone(), content.clone());
+                self.metadata.remove(&module);
+                self.replace_preview = None;
+            }
+            self.saved.insert(module, content);
+        }
+
+        if templates_modified != self.templates_modified {
+            self.templates = templates::list(&self.templates_path());
+            self.templates_modified = templates_modified;
+        }
+        let conflicts = std::mem::take(&mut self.conflicts);
+        self.conflicts = conflicts.into_iter().filter(|m| self.is_dirty(m)).collect();
+        self.folders = folders;
+        self.refresh_files();
+    }
+
+    fn conflict(&mut self, module: ModuleId, change: &str) {
+        self.file_errors.push(format!(
+            "{module} was {change} on disk, saving keeps your edits"
+        ));
+        self.conflicts.insert(module);
+    }
+
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        use iced::keyboard::{self, key::Named, Key};
+
+        // only sees keys no focused widget used, so the editor keeps its arrows
+        let navigation = keyboard::on_key_press(|key, modifiers| {
+            if modifiers.command() {
+                return None;
+            }
+            let message = match key {
+                Key::Named(Named::ArrowUp) => ModuleMessage::MoveCursor(-1),
+                Key::Named(Named::ArrowDown) => ModuleMessage::MoveCursor(1),
+                Key::Named(Named::Enter) => ModuleMessage::SubmitCursor,
+                _ => return None,
+            };
+            Some(Message::Editor(message))
+        });
+        iced::Subscription::batch([
+            iced::time::every(WATCH_INTERVAL).map(|_| Message::Editor(ModuleMessage::Rescan)),
+            navigation,
+        ])
+    }
+
+    pub fn view<'a>(&'a self) -> Element<'a, Message> {
+        let mut panes = vec![self.text_editor(self.active(), true)];
+        if let Some(tab) = self.split.as_ref().and_then(|m| self.tab(m)) {
+            panes.push(self.text_editor(Some(tab), false));
+        }
+        let editors = widget::column([
+            self.tab_bar(),
+            widget::row(panes).spacing(iced::Pixels(10.0)).into(),
+        ])
+        .spacing(iced::Pixels(5.0));
+
+        let mut columns = vec![self.file_select()];
+        if self.find_open {
+            columns.push(self.find_panel());
+        }
+        columns.push(editors.into());
+
+        let content = widget::row(columns)
+            .spacing(iced::Pixels(10.0))
+            .padding(iced::Padding::new(10.0));
+
+        Element::from(content)
+    }
+
+    pub fn update(&mut self, message: ModuleMessage) {
+        match message {
+            ModuleMessage::Editor(action) => {
+                if let Some(module) = self.files.selected().clone() {
+                    self.edit(&module, action);
+                }
+            }
+            ModuleMessage::SplitEditor(action) => {
+                if let Some(module) = self.split.clone() {
+                    self.edit(&module, action);
+                }
+            }
+            ModuleMessage::CloseTab => {
+                if let Some(module) = self.files.selected().clone() {
+                    self.close_tab(&module);
+                }
+            }
+            ModuleMessage::ToggleSplit => {
+                self.split = match self.split {
+                    Some(_) => None,
+                    // the most recently opened other tab
+                    None => self
+                        .tabs
+                        .iter()
+                        .rev()
+                        .map(|t| t.module.clone())
+                        .find(|m| Some(m) != self.files.selected().as_ref()),
+                };
+            }
+            ModuleMessage::SwapSplit => {
+                if let Some(split) = self.split.clone() {
+                    self.open(split);
+                }
+            }
+            ModuleMessage::AddModule => {
+                if self.module_add_text.is_empty() {
+                    return;
+                }
+                self.file_errors.clear();
+                let module = match self.new_module_id(&self.module_add_text) {
+                    Ok(module) => module,
+                    Err(e) => return self.file_errors.push(format!("add: {e}")),
+                };
+                let content = match &self.template {
+                    Some(template) => {
+                        match templates::instantiate(&self.templates_path(), template, &module) {
+                            Ok(content) => content,
+                            Err(e) => {
+                                return self.file_errors.push(format!("template {template}: {e}"))
+                            }
+                        }
+                    }
+                    None => String::new(),
+                };
+                if let Err(e) = write_atomic(&module.path(&self.path), &content) {
+                    return self.file_errors.push(format!("add: {e}"));
+                }
+
+                self.module_add_text.clear();
+                self.modules.insert(module.clone(), content.clone());
+                self.saved.insert(module.clone(), content);
+                self.refresh_folders();
+                self.refresh_files();
+                self.update(ModuleMessage::SelectModule(module));
+            }
+            ModuleMessage::SelectTemplate(template) => self.template = template,
+            ModuleMessage::AddModuleInput(input) => {
+                let input = input.replace(" ", "_");
+                self.module_add_text = input
+            }
+            ModuleMessage::Complete(label) => self.complete(label),
+            ModuleMessage::Undo => self.undo(false),
+            ModuleMessage::Redo => self.undo(true),
+            ModuleMessage::Save => self.save_modules(),
+            ModuleMessage::Rescan => self.rescan(),
+            ModuleMessage::SelectModule(module) => self.open(module),
+            ModuleMessage::RemoveModule(module) => {
+                self.file_errors.clear();
+                if let Err(e) = self.remove_module(&module) {
+                    self.file_errors.push(format!("delete: {e}"));
+                }
+                self.refresh_files();
+            }
+            ModuleMessage::RenameModule(module) => {
+                self.file_errors.clear();
+                let name = self.module_add_text.clone();
+                match self.rename_module(&module, &name) {
+                    Ok(()) => self.module_add_text.clear(),
+                    Err(e) => self.file_errors.push(format!("rename: {e}")),
+                }
+                self.refresh_files();
+            }
+            ModuleMessage::DuplicateModule(module) => {
+                self.file_errors.clear();
+                let name = if self.module_add_text.is_empty() {
+                    format!("{}_copy", module.as_path().to_string_lossy())
+                } else {
+                    self.module_add_text.clone()
+                };
+                match self.duplicate_module(&module, &name) {
+                    Ok(()) => self.module_add_text.clear(),
+                    Err(e) => self.file_errors.push(format!("duplicate: {e}")),
+                }
+                self.refresh_files();
+            }
+            ModuleMessage::AddFolder => {
+                self.file_errors.clear();
+                let folder = Path::new(&self.module_add_text);
+                if !id::is_valid_path(folder) {
+                    self.file_errors.push("folder: invalid folder name".into());
+                } else if let Err(e) = fs::create_dir_all(self.path.join(folder)) {
+                    self.file_errors.push(format!("folder: {e}"));
+                } else {
+                    self.tree.expand(folder);
+                    self.module_add_text.clear();
+                    self.refresh_folders();
+                }
+                self.refresh_files();
+            }
+            ModuleMessage::Tree(event) => match self.tree.update(event) {
+                Some(tree::Action::Select(module)) => {
+                    self.update(ModuleMessage::SelectModule(module))
+                }
+                Some(tree::Action::Move(module, folder)) => {
+                    self.file_errors.clear();
+                    if let Err(e) = self.move_module(&module, &folder) {
+                        self.file_errors.push(format!("move: {e}"));
+                    }
+                    self.refresh_files();
+                }
+                None => (),
+            },
+            ModuleMessage::ToggleTree(tree_view) => self.tree_view = tree_view,
+            ModuleMessage::Search(search) => {
+                self.search = search;
+                self.refresh_files();
+                self.files.reset_cursor();
+            }
+            ModuleMessage::SearchContents(search_contents) => {
+                self.search_contents = search_contents;
+                self.refresh_files();
+                self.files.reset_cursor();
+            }
+            ModuleMessage::FilterTag(tag) => {
+                self.tag_filter = Some(tag);
+                self.refresh_files();
+            }
+            ModuleMessage::ClearTagFilter => {
+                self.tag_filter = None;
+                self.refresh_files();
+            }
+            ModuleMessage::ToggleFavourite(module) => {
+                self.file_errors.clear();
+                if !self.favourites.remove(&module) {
+                    self.favourites.insert(module);
+                }
+                self.save_favourites();
+                self.refresh_files();
+            }
+            ModuleMessage::FavouritesOnly(favourites_only) => {
+                self.favourites_only = favourites_only;
+                self.refresh_files();
+            }
+            ModuleMessage::MoveCursor(delta) => {
+                if self.list_view() {
+                    self.files.move_cursor(delta);
+                }
+            }
+            ModuleMessage::SubmitCursor => {
+                if let Some(Message::Editor(message)) =
+                    self.list_view().then(|| self.files.submit()).flatten()
+                {
+                    self.update(message);
+                }
+            }
+            ModuleMessage::CompileModule => {
+                if let Some(tab) = self.active_mut() {
+                    tab.compile();
+                }
+            }
+            ModuleMessage::TestModule => {
+                let Some(tab) = self.active_mut() else {
+                    return;
+                };
+                if let Err(diagnostic) = tab.test() {
+                    self.file_errors
+                        .push(format!("test: {}", diagnostic.summary()));
+                }
+            }
+            ModuleMessage::ProfileModule => {
+                let rate = audio::get().map_or(TEST_RATE, |e| e.sample_rate());
+                if let Some(tab) = self.active_mut() {
+                    tab.profile(rate);
+                }
+            }
+            ModuleMessage::Stop => {
+                if let Some(engine) = audio::get() {
+                    engine.stop();
+                }
+            }
+            ModuleMessage::ToggleLive(live) => {
+                self.live = live;
+                for tab in &mut self.tabs {
+                    tab.last_edit = None;
+                }
+            }
+            ModuleMessage::Format => {
+                self.file_errors.clear();
+                if let Some(module) = self.files.selected().clone() {
+                    if let Err(e) = self.format_module(&module) {
+                        self.file_errors.push(format!("format {module}: {e}"));
+                    }
+                }
+            }
+            ModuleMessage::FormatOnSave(format) => self.format_on_save = format,
+            ModuleMessage::ToggleHistory(open) => {
+                self.history = match (open, self.files.selected()) {
+                    (true, Some(module)) => Some(HistoryView {
+                        module: module.clone(),
+                        snapshots: history::list(&self.history_path(module)),
+                        selected: None,
+                        content: String::new(),
+                        preview: Err(Diagnostic::new("")),
+                        render: None,
+                        diff: Vec::new(),
+                    }),
+                    _ => None,
+                };
+            }
+            ModuleMessage::ToggleFind(open) => {
+                self.find_open = open;
+                self.refresh_files();
+            }
+            ModuleMessage::FindInput(text) => {
+                self.find_text = text;
+                self.replace_preview = None;
+                self.refresh_files();
+            }
+            ModuleMessage::ReplaceInput(text) => {
+                self.replace_text = text;
+                self.replace_preview = None;
+            }
+            ModuleMessage::FindOptions(options) => {
+                self.find_options = options;
+                self.replace_preview = None;
+                self.refresh_files();
+            }
+            ModuleMessage::FindGoto(module, line, column) => {
+                self.open(module);
+                if let Some(tab) = self.active_mut() {
+                    let text = tab.content.text();
+                    for action in crate::diagnostic::move_to(&text, line, column) {
+                        tab.content.perform(action);
+                    }
+                }
+            }
+            ModuleMessage::PreviewReplace => self.preview_replace(),
+            ModuleMessage::ReplaceAll => self.replace_all(),
+            ModuleMessage::SelectSnapshot(index) => self.select_snapshot(index),
+            ModuleMessage::RestoreSnapshot => self.restore_snapshot(),
+            ModuleMessage::GotoDiagnostic(diagnostic) => {
+                if let Some(tab) = self.active_mut() {
+                    for action in diagnostic.goto(&tab.content.text()) {
+                        tab.content.perform(action);
+                    }
+                }
+            }
+        };
+    }
+
+    pub fn tick(&mut self) {
+        for i in 0..self.tabs.len() {
+            self.tabs[i].poll_profile();
+            match self.tabs[i].poll() {
+                Some(Finished::Compiled) => self.record_compiled(&self.tabs[i]),
+                Some(Finished::Play(samples)) => match audio::get() {
+                    Some(engine) => engine.play_mono(samples),
+                    None => self.file_errors.push("test: no audio output device".into()),
+                },
+                None => (),
+            }
+        }
+        if let Some(history) = &mut self.history {
+            if let Some(result) = history.render.as_mut().and_then(Render::poll) {
+                history.preview = result.map(|r| r.samples);
+                history.render = None;
+            }
+        }
+
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.write_recovery();
+            self.last_autosave = Instant::now();
+        }
+        if self.live {
+            // only COMPILE snapshots, live mode compiles at every pause in typing
+            for tab in &mut self.tabs {
+                tab.live_compile();
+            }
+        }
+    }
+
+    /// Snapshot list next to the render and the diff of the selected snapshot.
+    fn history_view<'a>(&'a self, history: &'a HistoryView) -> Element<'a, Message> {
+        let mut snapshots = history
+            .snapshots
+            .iter()
+            .enumerate()
+            .map(|(i, snapshot)| {
+                let mut select = widget::button(widget::text(snapshot.label()).size(14))
+                    .on_press(Message::Editor(ModuleMessage::SelectSnapshot(i)))
+                    .width(iced::Length::Fill);
+                if Some(i) == history.selected {
+                    select = select.style(iced::theme::Button::Positive);
+                }
+                select.into()
+            })
+            .collect::<Vec<Element<_>>>();
+        if snapshots.is_empty() {
+            snapshots.push(widget::text("no snapshots yet").into());
+        }
+        let restore = widget::button(widget::text("RESTORE"))
+            .on_press_maybe(
+                history
+                    .selected
+                    .map(|_| Message::Editor(ModuleMessage::RestoreSnapshot)),
+            )
+            .width(iced::Length::Fill);
+        let list = widget::column([
+            widget::scrollable(widget::column(snapshots).spacing(iced::Pixels(2.0)))
+                .height(iced::Length::Fill)
+                .into(),
+            restore.into(),
+        ])
+        .spacing(iced::Pixels(5.0))
+        .width(iced::Length::Fixed(160.0));
+
+        let preview: Element<'_, _> = match &history.preview {
+            Ok(points) => graph::Graph::new(points.clone()).scale(0.5).into(),
+            Err(diagnostic) => self.diagnostics(std::slice::from_ref(diagnostic), false),
+        };
+        let diff = history.diff.iter().map(|line| {
+            let (prefix, line, style) = match line {
+                diff::Line::Same(line) => ("  ", line, iced::theme::Text::Default),
+                diff::Line::Added(line) => (
+                    "+ ",
+                    line,
+                    iced::theme::Text::Color(iced::Color::from_rgb(0.5, 1.0, 0.5)),
+                ),
+                diff::Line::Removed(line) => (
+                    "- ",
+                    line,
+                    iced::theme::Text::Color(iced::Color::from_rgb(1.0, 0.5, 0.5)),
+                ),
+            };
+            widget::text(format!("{prefix}{line}"))
+                .font(iced::Font::MONOSPACE)
+                .size(14)
+                .style(style)
+                .into()
+        });
+        let diff = widget::scrollable(widget::column(diff.collect::<Vec<_>>()))
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill);
+
+        widget::row([
+            list.into(),
+            widget::column([
+                widget::container(preview)
+                    .height(iced::Length::FillPortion(1))
+                    .into(),
+                widget::container(diff)
+                    .height(iced::Length::FillPortion(1))
+                    .into(),
+            ])
+            .spacing(iced::Pixels(5.0))
+            .into(),
+        ])
+        .spacing(iced::Pixels(5.0))
+        .padding(iced::Padding::new(5.0))
+        .into()
+    }
+
+    /// Render or errors of the tab, `primary` for the left pane which also shows the
+    /// history browser and takes the cursor to clicked diagnostics.
+    fn output<'a>(&'a self, tab: Option<&'a Tab>, primary: bool) -> Element<'a, Message> {
+        let inner: Element<'_, _> = match (tab, &self.history) {
+            (Some(_), Some(history)) if primary => self.history_view(history),
+            (Some(tab), _) => self.preview(tab, primary),
+            (None, _) => widget::column([]).into(),
+        };
+
+        let inner = widget::container(inner)
+            .height(iced::Length::FillPortion(1))
+            .width(iced::Length::Fill)
+            .style(iced::theme::Container::Box);
+
+        inner.into()
+    }
+
+    fn preview<'a>(&'a self, tab: &'a Tab, primary: bool) -> Element<'a, Message> {
+        let inner: Element<'_, _> = match &tab.preview {
+            Ok(points) if !tab.lints.is_empty() => {
+                let graph = graph::Graph::new(points.clone()).scale(0.5);
+                widget::row([graph.into(), self.lints(&tab.lints)])
+                    .spacing(iced::Pixels(5.0))
+                    .into()
+            }
+            Ok(points) => {
+                let graph = graph::Graph::new(points.clone()).scale(0.5);
+
+                graph.into()
+            }
+            Err(diagnostic) => self.diagnostics(std::slice::from_ref(diagnostic), primary),
+        };
+        match (&tab.preview, &tab.live_error) {
+            (Ok(_), Some(diagnostic)) => widget::column([
+                inner,
+                widget::container(self.diagnostics(std::slice::from_ref(diagnostic), primary))
+                    .height(iced::Length::FillPortion(1))
+                    .into(),
+            ])
+            .into(),
+            _ => inner,
+        }
+    }
+
+    /// List of diagnostics, if `clickable` clicking one moves the editor cursor to the
+    /// failing line.
+    fn diagnostics<'a>(
+        &'a self,
+        diagnostics: &[Diagnostic],
+        clickable: bool,
+    ) -> Element<'a, Message> {
+        let list = diagnostics
+            .iter()
+            .filter(|d| !d.message.is_empty())
+            .map(|d| {
+                let mut goto = widget::button(widget::text(d.summary()))
+                    .style(iced::theme::Button::Destructive)
+                    .width(iced::Length::Fill);
+                if clickable && d.span.is_some() {
+                    goto = goto.on_press(Message::Editor(ModuleMessage::GotoDiagnostic(d.clone())));
+                }
+                widget::column([goto.into(), widget::text(d.message.clone()).into()]).into()
+            })
+            .collect::<Vec<Element<_>>>();
+
+        widget::scrollable(
+            widget::column(list)
+                .spacing(iced::Pixels(5.0))
+                .padding(iced::Padding::new(5.0)),
+        )
+        .height(iced::Length::Fill)
+        .into()
+    }
+
+    /// Warnings about the test render, shown next to the graph.
+    fn lints<'a>(&'a self, lints: &[String]) -> Element<'a, Message> {
+        let list = lints
+            .iter()
+            .map(|l| {
+                widget::text(l)
+                    .style(iced::theme::Text::Color(iced::Color::from_rgb(
+                        1.0, 0.8, 0.4,
+                    )))
+                    .into()
+            })
+            .collect::<Vec<Element<_>>>();
+
+        widget::scrollable(
+            widget::column(list)
+                .spacing(iced::Pixels(5.0))
+                .padding(iced::Padding::new(5.0)),
+        )
+        .width(iced::Length::Fixed(250.0))
+        .height(iced::Length::Fill)
+        .into()
+    }
+
+    /// Project wide find and replace, listing the hits or the preview of a replace.
+    fn find_panel<'a>(&'a self) -> Element<'a, Message> {
+        let find = widget::text_input("find in project", &self.find_text)
+            .id(widget::text_input::Id::new(FIND_INPUT))
+            .on_input(|text| Message::Editor(ModuleMessage::FindInput(text)));
+        let replace = widget::text_input("replace with", &self.replace_text)
+            .on_input(|text| Message::Editor(ModuleMessage::ReplaceInput(text)))
+            .on_submit(Message::Editor(ModuleMessage::PreviewReplace));
+
+        let options = self.find_options;
+        let match_case =
+            widget::checkbox("CASE", options.match_case).on_toggle(move |match_case| {
+                Message::Editor(ModuleMessage::FindOptions(find::Options {
+                    match_case,
+                    ..options
+                }))
+            });
+        let whole_word =
+            widget::checkbox("WORD", options.whole_word).on_toggle(move |whole_word| {
+                Message::Editor(ModuleMessage::FindOptions(find::Options {
+                    whole_word,
+                    ..options
+                }))
+            });
+        let options =
+            widget::row([match_case.into(), whole_word.into()]).spacing(iced::Pixels(10.0));
+
+        let searching = !self.find_text.is_empty();
+        let preview = widget::button(widget::text("PREVIEW"))
+            .on_press_maybe(searching.then_some(Message::Editor(ModuleMessage::PreviewReplace)))
+            .width(iced::Length::Fill);
+        let apply = widget::button(widget::text("REPLACE ALL"))
+            .on_press_maybe(
+                self.replace_preview
+                    .as_ref()
+                    .map(|_| Message::Editor(ModuleMessage::ReplaceAll)),
+            )
+            .style(iced::theme::Button::Destructive)
+            .width(iced::Length::Fill);
+        let actions = widget::row([preview.into(), apply.into()]).spacing(iced::Pixels(5.0));
+
+        let mut modules = self.hits.iter().map(|h| &h.module).collect::<Vec<_>>();
+        modules.dedup();
+        let summary = widget::text(format!(
+            "{} hits in {} modules",
+            self.hits.len(),
+            modules.len()
+        ));
+
+        let list: Vec<Element<_>> = match &self.replace_preview {
+            Some(preview) => preview
+                .iter()
+                .flat_map(|Replacement { module, lines, .. }| {
+                    let lines = lines.iter().map(|line| {
+                        let (line, color) = match line {
+                            diff::Line::Added(line) => (format!("+ {line}"), (0.5, 1.0, 0.5)),
+                            diff::Line::Removed(line) => (format!("- {line}"), (1.0, 0.5, 0.5)),
+                            diff::Line::Same(line) => (format!("  {line}"), (1.0, 1.0, 1.0)),
+                        };
+                        let (r, g, b) = color;
+                        widget::text(line)
+                            .font(iced::Font::MONOSPACE)
+                            .size(14)
+                            .style(iced::theme::Text::Color(iced::Color::from_rgb(r, g, b)))
+                            .into()
+                    });
+                    std::iter::once(widget::text(module.to_string()).into()).chain(lines)
+                })
+                .collect(),
+            None => self
+                .hits
+                .iter()
+                .take(HIT_LIMIT)
+                .map(|hit| {
+                    let label = format!("{}:{}  {}", hit.module, hit.line + 1, hit.text.trim());
+                    widget::button(widget::text(label).size(14))
+                        .on_press(Message::Editor(ModuleMessage::FindGoto(
+                            hit.module.clone(),
+                            hit.line,
+                            hit.range.start,
+                        )))
+                        .style(iced::theme::Button::Text)
+                        .width(iced::Length::Fill)
+                        .into()
+                })
+                .collect(),
+        };
+        let list = widget::scrollable(widget::column(list).spacing(iced::Pixels(2.0)))
+            .height(iced::Length::Fill);
+
+        let content = widget::column([
+            find.into(),
+            replace.into(),
+            options.into(),
+            actions.into(),
+            summary.into(),
+            list.into(),
+        ])
+        .spacing(iced::Pixels(5.0));
+        widget::container(content)
+            .width(iced::Length::Fixed(300.0))
+            .into()
+    }
+
+    /// Open tabs with the buttons managing them.
+    fn tab_bar<'a>(&'a self) -> Element<'a, Message> {
+        let selected = self.files.selected().is_some();
+        let button = |label, message, enabled: bool| {
+            widget::button(widget::text(label))
+                .on_press_maybe(enabled.then_some(Message::Editor(message)))
+                .into()
+        };
+        widget::row([
+            widget::container(self.tab_bar.clone())
+                .width(iced::Length::Fill)
+                .into(),
+            button("CLOSE", ModuleMessage::CloseTab, selected),
+            button("SPLIT", ModuleMessage::ToggleSplit, self.tabs.len() > 1),
+            button("SWAP", ModuleMessage::SwapSplit, self.split.is_some()),
+        ])
+        .spacing(iced::Pixels(5.0))
+        .into()
+    }
+
+    /// Editor and render of a tab, `primary` for the left pane.
+    fn text_editor<'a>(&'a self, tab: Option<&'a Tab>, primary: bool) -> Element<'a, Message> {
+        let content = tab.map_or(&self.blank, |t| &t.content);
+        let mut text = TextEditor::new(content)
+            .padding(iced::Padding::new(10.0))
+            .height(iced::Length::Fill);
+        if tab.is_some() {
+            text = match primary {
+                true => text.on_action(|a| Message::Editor(ModuleMessage::Editor(a))),
+                false => text.on_action(|a| Message::Editor(ModuleMessage::SplitEditor(a))),
+            };
+        }
+        let settings = highlighter::Settings {
+            diagnostics: tab
+                .and_then(|t| t.live_error.as_ref().or(t.preview.as_ref().err()))
+                .and_then(|d| d.span)
+                .into_iter()
+                .collect(),
+        };
+        let text = text.highlight::<Highlighter>(settings, highlighter::to_format);
+        let text = widget::container(text).height(iced::Length::FillPortion(2));
+        let output = self.output(tab, primary);
+
+        let mut content = vec![text.into()];
+        if let Some(tab) = tab.filter(|_| primary) {
+            content.extend(self.completion(tab));
+        }
+        content.push(output);
+        let content = widget::Column::with_children(content).spacing(iced::Pixels::from(5.0));
+        // let content = text;
+
+        let centered = widget::container(content)
+            .height(iced::Length::Fill)
+            .align_x(iced::alignment::Horizontal::Center)
+            .align_y(iced::alignment::Vertical::Center);
+
+        Element::from(centered)
+    }
+
+    /// Signature hint and completions for the cursor of the tab, if there are any.
+    fn completion<'a>(&'a self, tab: &'a Tab) -> Option<Element<'a, Message>> {
+        let hint = tab.hint();
+        let (_, items) = tab.completions();
+        if hint.is_none() && items.is_empty() {
+            return None;
+        }
+
+        let mut list: Vec<Element<_>> = Vec::new();
+        if let Some(hint) = hint {
+            list.push(
+                widget::text(hint.to_string())
+                    .font(iced::Font::MONOSPACE)
+                    .size(14)
+                    .into(),
+            );
+        }
+        for item in items {
+            let label = widget::row([
+                widget::text(&item.label)
+                    .font(iced::Font::MONOSPACE)
+                    .width(iced::Length::Fill)
+                    .into(),
+                widget::text(&item.detail).size(14).into(),
+            ]);
+            list.push(
+                widget::button(label)
+                    .on_press(Message::Editor(ModuleMessage::Complete(Some(item.label))))
+                    .style(iced::theme::Button::Text)
+                    .width(iced::Length::Fill)
+                    .into(),
+            );
+        }
+        let list = widget::container(widget::column(list).padding(iced::Padding::new(5.0)))
+            .width(iced::Length::Fill)
+            .style(iced::theme::Container::Box);
+        Some(list.into())
+    }
+
+    /// Favourite toggle and metadata of the selected module.
+    fn module_info<'a>(&'a self) -> Element<'a, Message> {
+        let Some(module) = self.files.selected() else {
+            return widget::column([]).into();
+        };
+        let label = match self.favourites.contains(module) {
+            true => "UNFAVOURITE",
+            false => "FAVOURITE",
+        };
+        let favourite = widget::button(widget::text(label))
+            .on_press(Message::Editor(ModuleMessage::ToggleFavourite(
+                module.clone(),
+            )))
+            .width(iced::Length::Fill);
+
+        let info = self
+            .metadata(module)
+            .summary()
+            .into_iter()
+            .map(|line| widget::text(line).size(14).into());
+        widget::column(
+            std::iter::once(favourite.into())
+                .chain(info)
+                .collect::<Vec<_>>(),
+        )
+        .spacing(iced::Pixels(2.0))
+        .into()
+    }
+
+    fn file_select<'a>(&'a self) -> Element<'a, Message> {
+        let add_module = widget::text_input("add module", self.module_add_text.as_str())
+            .id(widget::text_input::Id::new(ADD_MODULE_INPUT))
+            .on_input(|input| Message::Editor(ModuleMessage::AddModuleInput(input)))
+            .on_submit(Message::Editor(ModuleMessage::AddModule));
+        let add_folder =
+            widget::button(widget::text("DIR")).on_press(Message::Editor(ModuleMessage::AddFolder));
+        let add_module =
+            widget::row([add_module.into(), add_folder.into()]).spacing(iced::Pixels(5.0));
+        let template = widget::pick_list(self.templates.clone(), self.template.clone(), |t| {
+            Message::Editor(ModuleMessage::SelectTemplate(Some(t)))
+        })
+        .placeholder("empty file")
+        .width(iced::Length::Fill);
+        let no_template = widget::button(widget::text("X")).on_press_maybe(
+            self.template
+                .as_ref()
+                .map(|_| Message::Editor(ModuleMessage::SelectTemplate(None))),
+        );
+        let template =
+            widget::row([template.into(), no_template.into()]).spacing(iced::Pixels(5.0));
+
+        let save = widget::button(widget::text("SAVE"))
+            .on_press(Message::Editor(ModuleMessage::Save))
+            .width(iced::Length::Fill);
+        let format = widget::button(widget::text("FORMAT"))
+            .on_press_maybe(
+                self.files
+                    .selected()
+                    .as_ref()
+                    .map(|_| Message::Editor(ModuleMessage::Format)),
+            )
+            .width(iced::Length::Fill);
+        let find = widget::button(widget::text("FIND"))
+            .on_press(Message::Editor(ModuleMessage::ToggleFind(!self.find_open)))
+            .width(iced::Length::Fill);
+        let save =
+            widget::row([save.into(), format.into(), find.into()]).spacing(iced::Pixels(5.0));
+        let save = widget::container(save)
+            .height(iced::Length::Shrink)
+            .width(iced::Length::Fill)
+            .align_x(iced::alignment::Horizontal::Center)
+            .align_y(iced::alignment::Vertical::Center);
+
+        let errors = self.file_errors.iter().map(|e| {
+            widget::text(e)
+                .style(iced::theme::Text::Color(iced::Color::from_rgb(
+                    1.0, 0.5, 0.5,
+                )))
+                .into()
+        });
+        let errors = widget::column(errors.collect::<Vec<_>>());
+
+        let module_button = |label, message: fn(ModuleId) -> ModuleMessage| {
+            widget::button(widget::text(label))
+                .on_press_maybe(
+                    self.files
+                        .selected()
+                        .clone()
+                        .map(|m| Message::Editor(message(m))),
+                )
+                .width(iced::Length::Fill)
+                .into()
+        };
+        let file_actions = widget::row([
+            module_button("RENAME", ModuleMessage::RenameModule),
+            module_button("COPY", ModuleMessage::DuplicateModule),
+            module_button("DELETE", ModuleMessage::RemoveModule),
+        ])
+        .spacing(iced::Pixels(5.0));
+
+        let mut tags = self
+            .metadata
+            .values()
+            .flat_map(|m| m.tags.iter().cloned())
+            .collect::<Vec<_>>();
+        tags.sort_unstable();
+        tags.dedup();
+        let tag_filter = widget::pick_list(tags, self.tag_filter.clone(), |tag| {
+            Message::Editor(ModuleMessage::FilterTag(tag))
+        })
+        .placeholder("tag")
+        .width(iced::Length::Fill);
+        let clear_tag = widget::button(widget::text("X")).on_press_maybe(
+            self.tag_filter
+                .as_ref()
+                .map(|_| Message::Editor(ModuleMessage::ClearTagFilter)),
+        );
+        let tag_filter =
+            widget::row([tag_filter.into(), clear_tag.into()]).spacing(iced::Pixels(5.0));
+        let favourites_only = widget::checkbox("FAVOURITES", self.favourites_only)
+            .on_toggle(|only| Message::Editor(ModuleMessage::FavouritesOnly(only)));
+
+        let files = match self.list_view() {
+            true => self.files.clone().into(),
+            false => self.tree.view(self.files.selected().as_ref()),
+        };
+        let tree_view = widget::checkbox("TREE", self.tree_view)
+            .on_toggle(|tree_view| Message::Editor(ModuleMessage::ToggleTree(tree_view)));
+        let search = widget::text_input("search", &self.search)
+            .id(widget::text_input::Id::new(SEARCH_INPUT))
+            .on_input(|search| Message::Editor(ModuleMessage::Search(search)))
+            .on_submit(Message::Editor(ModuleMessage::SubmitCursor));
+        let search_contents =
+            widget::checkbox("CONTENTS", self.search_contents).on_toggle(|search_contents| {
+                Message::Editor(ModuleMessage::SearchContents(search_contents))
+            });
+        let view_options =
+            widget::row([tree_view.into(), search_contents.into()]).spacing(iced::Pixels(10.0));
+
+        let compile = widget::button(widget::text("COMPILE"))
+            .on_press(Message::Editor(ModuleMessage::CompileModule))
+            .width(iced::Length::Fill);
+        let test = widget::button(widget::text("TEST"))
+            .on_press(Message::Editor(ModuleMessage::TestModule))
+            .width(iced::Length::Fill);
+
+        let profile = widget::button(widget::text("PROFILE"))
+            .on_press_maybe(
+                self.active()
+                    .filter(|t| !t.is_profiling())
+                    .map(|_| Message::Editor(ModuleMessage::ProfileModule)),
+            )
+            .width(iced::Length::Fill);
+
+        let ct = widget::row([
+            compile.into(),
+            widget::horizontal_space()
+                .width(iced::Length::Fixed(5.0))
+                .into(),
+            test.into(),
+            widget::horizontal_space()
+                .width(iced::Length::Fixed(5.0))
+                .into(),
+            profile.into(),
+        ]);
+        let profile = match self.active().and_then(|t| t.profile.as_ref()) {
+            Some(Ok(profile)) => {
+                let color = match profile.realtime_factor() >= 1.0 {
+                    true => iced::Color::from_rgb(0.5, 1.0, 0.5),
+                    false => iced::Color::from_rgb(1.0, 0.5, 0.5),
+                };
+                widget::text(profile.report())
+                    .size(14)
+                    .style(iced::theme::Text::Color(color))
+            }
+            Some(Err(diagnostic)) => {
+                widget::text(diagnostic.summary())
+                    .size(14)
+                    .style(iced::theme::Text::Color(iced::Color::from_rgb(
+                        1.0, 0.5, 0.5,
+                    )))
+            }
+            None => widget::text(""),
+        };
+        let live = widget::checkbox("LIVE", self.live)
+            .on_toggle(|live| Message::Editor(ModuleMessage::ToggleLive(live)));
+        let history = widget::checkbox("HISTORY", self.history.is_some()).on_toggle_maybe(
+            self.files
+                .selected()
+                .as_ref()
+                .map(|_| |open| Message::Editor(ModuleMessage::ToggleHistory(open))),
+        );
+        let live = widget::row([live.into(), history.into()]).spacing(iced::Pixels(10.0));
+        let format_on_save = widget::checkbox("FORMAT ON SAVE", self.format_on_save)
+            .on_toggle(|format| Message::Editor(ModuleMessage::FormatOnSave(format)));
+
+        // let content = widget::list_column().add(save).add(add_module).add(files);
+        let content = widget::column([
+            ct.into(),
+            profile.into(),
+            live.into(),
+            format_on_save.into(),
+            save.into(),
+            errors.into(),
+            add_module.into(),
+            template.into(),
+            file_actions.into(),
+            widget::vertical_space()
+                .height(iced::Length::Fixed(10.0))
+                .into(),
+            self.module_info(),
+            widget::vertical_space()
+                .height(iced::Length::Fixed(10.0))
+                .into(),
+            search.into(),
+            view_options.into(),
+            tag_filter.into(),
+            favourites_only.into(),
+            files,
+        ]);
+        let content = widget::container(content).width(iced::Length::Fixed(200.0));
+
+        Element::from(content)
+    }
+}
+
+fn compile(module: &str) -> Result<Arc<bs::executor::Executor>, Diagnostic> {
+    let tokens = bs::lexer::tokenize(module);
+    let ast = match bs::parser::parse(tokens) {
+        Ok(a) => bs::parser::Ast::new(a),
+        Err(e) => return Err(Diagnostic::from_error(module, "parse error", &e)),
+    };
+    match bs::executor::Executor::build(ast) {
+        Ok(e) => Ok(Arc::new(e)),
+        Err(e) => Err(Diagnostic::from_error(module, "build error", &e)),
+    }
+}
+
+/// Samples rendered by TEST, as many as the module's `@length` asks for.
+fn test_length(source: &str) -> usize {
+    let length = Metadata::parse(source)
+        .length
+        .unwrap_or(1.0)
+        .min(audio::BUFFER_SECONDS as f64);
+    (length * TEST_RATE as f64) as usize
+}
+
+/// Time a render of `seconds` of audio may take before it is aborted.
+fn render_budget(seconds: f64) -> Duration {
+    RENDER_BUDGET + Duration::from_secs_f64(seconds * SLOWEST)
+}
+
+/// Time the TEST render of `samples` may take before it is aborted.
+fn test_budget(samples: usize) -> Duration {
+    render_budget(samples as f64 / TEST_RATE as f64)
+}
+
+/// Compiles and renders the module like TEST, returning the `@expect` checks it fails.
+pub fn check_expectations(source: &str) -> Result<Vec<String>, Diagnostic> {
+    let length = test_length(source);
+    let samples = Render::start(source, "main", 0..length, TEST_SCALE, test_budget(length))?
+        .wait()?
+        .samples;
+    let expectations = Metadata::parse(source).expect;
+    Ok(expect::failures(&expectations, &samples, TEST_RATE))
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it over `path`,
+/// so a crash mid-save never leaves a truncated module behind.
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let Some(name) = path.file_name() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "missing file name",
+        ));
+    };
+    let mut temp = OsString::from(".");
+    temp.push(name);
+    temp.push(".tmp");
+    let temp = path.with_file_name(temp);
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Collects the folders below `root`, relative to it.
+fn read_folders(root: &Path, folder: &Path, folders: &mut Vec<PathBuf>) {
+    let Ok(dir) = fs::read_dir(root.join(folder)) else {
+        return;
+    };
+    for entry in dir.into_iter() {
+        let Ok(entry) = entry else { continue };
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let folder = folder.join(entry.file_name());
+            read_folders(root, &folder, folders);
+            folders.push(folder);
+        }
+    }
+}
+
+/// Error for a module id that is not part of the project.
+fn not_loaded(module: &ModuleId) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{module} is not loaded"))
+}
+
+/// Collects the modification time of every module file below `dir`, and the folders
+/// in the order [`read_folders`] lists them.
+fn read_mtimes(
+    root: &Path,
+    dir: fs::ReadDir,
+    mtimes: &mut HashMap<ModuleId, SystemTime>,
+    folders: &mut Vec<PathBuf>,
+) {
+    for entry in dir.into_iter() {
+        let Ok(entry) = entry else { continue };
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            let Some(name) = ModuleId::from_path(root, &entry.path()) else {
+                continue;
+            };
+            mtimes.insert(name, metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+        } else if metadata.is_dir() {
+            let Ok(dir) = fs::read_dir(entry.path()) else {
+                continue;
+            };
+            read_mtimes(root, dir, mtimes, folders);
+            if let Ok(folder) = entry.path().strip_prefix(root) {
+                folders.push(folder.to_path_buf());
+            }
+        }
+    }
+}
+
+fn read_dir(root: &Path, dir: fs::ReadDir, modules: &mut HashMap<ModuleId, String>) {
+    for entry in dir.into_iter() {
+        let Ok(entry) = entry else { continue };
+        // skips the temp files of atomic saves
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_file() {
+            let Some(name) = ModuleId::from_path(root, &entry.path()) else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            modules.insert(name, content);
+        } else if file_type.is_dir() {
+            let Ok(dir) = fs::read_dir(entry.path()) else {
+                continue;
+            };
+            read_dir(root, dir, modules)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path in the temp folder that no other test uses, nothing exists there yet. Tests
+    /// run in parallel, so the name alone would not keep them apart.
+    pub(super) fn temp_folder(name: &str) -> PathBuf {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("mksnd-{name}-{}-{count}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    /// Empty project in the temp folder.
+    fn project(name: &str) -> PathBuf {
+        let path = temp_folder(name);
+        fs::create_dir_all(path.join("modules")).unwrap();
+        path
+    }
+
+    /// Writes a module file with a modification time `seconds` after the epoch.
+    fn write_module(project: &Path, name: &str, content: &str, seconds: u64) {
+        let path = project.join("modules").join(name);
+        fs::write(&path, content).unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn rescan_reads_only_changed_files() {
+        let project = project("rescan");
+        write_module(&project, "a", "fn a() {}\n", 1);
+        let mut modules = Modules::new(project.clone());
+        let module = ModuleId::new("a").unwrap();
+
+        // an unchanged time means the file is not read again
+        write_module(&project, "a", "fn b() {}\n", 1);
+        modules.rescan();
+        assert_eq!(modules.modules[&module], "fn a() {}\n");
+
+        write_module(&project, "a", "fn c() {}\n", 2);
+        write_module(&project, "b", "fn d() {}\n", 2);
+        modules.rescan();
+        assert_eq!(modules.modules[&module], "fn c() {}\n");
+        assert!(modules.modules.contains_key(&ModuleId::new("b").unwrap()));
+
+        fs::remove_file(project.join("modules/b")).unwrap();
+        modules.rescan();
+        assert!(!modules.modules.contains_key(&ModuleId::new("b").unwrap()));
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn rescan_without_changes_refreshes_nothing() {
+        let project = project("rescan-unchanged");
+        write_module(&project, "a", "fn a() {}\n", 1);
+        let mut modules = Modules::new(project.clone());
+        modules.templates.clear();
+        modules.rescan();
+        assert!(modules.templates.is_empty());
+
+        fs::create_dir_all(project.join("templates")).unwrap();
+        fs::write(project.join("templates/pad"), "").unwrap();
+        modules.rescan();
+        assert_eq!(modules.templates, ["pad"]);
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn modules_deleted_on_disk_can_be_removed() {
+        let project = project("remove-deleted");
+        write_module(&project, "a", "fn a() {}\n", 1);
+        let mut modules = Modules::new(project.clone());
+        let module = ModuleId::new("a").unwrap();
+        fs::remove_file(project.join("modules/a")).unwrap();
+
+        modules.remove_module(&module).unwrap();
+        assert!(!modules.modules.contains_key(&module));
+        let missing = ModuleId::new("missing").unwrap();
+        assert!(modules.remove_module(&missing).is_err());
+        assert!(modules.duplicate_module(&missing, "copy").is_err());
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn favourites_survive_a_reload() {
+        let project = project("favourites");
+        write_module(&project, "kick", "fn main() {}\n", 1);
+        let mut modules = Modules::new(project.clone());
+        let kick = ModuleId::new("kick").unwrap();
+        modules.favourites.insert(kick.clone());
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = std::ffi::OsStr::from_bytes(b"bad\xffname");
+            modules.favourites.insert(ModuleId::new(name).unwrap());
+        }
+        modules.save_favourites();
+
+        let modules = Modules::new(project.clone());
+        assert_eq!(modules.favourites, HashSet::from([kick]));
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn folders_are_read_on_rescan() {
+        let project = project("folders");
+        fs::create_dir_all(project.join("modules/drums")).unwrap();
+        let mut modules = Modules::new(project.clone());
+        assert_eq!(modules.folders, [PathBuf::from("drums")]);
+
+        fs::create_dir_all(project.join("modules/bass")).unwrap();
+        modules.refresh_files();
+        assert_eq!(modules.folders, [PathBuf::from("drums")]);
+        modules.rescan();
+        modules.folders.sort();
+        assert_eq!(
+            modules.folders,
+            [PathBuf::from("bass"), PathBuf::from("drums")]
+        );
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn metadata_follows_changes_on_disk() {
+        let project = project("metadata");
+        write_module(&project, "a", "// @tags kick\n", 1);
+        let mut modules = Modules::new(project.clone());
+        let module = ModuleId::new("a").unwrap();
+        assert_eq!(modules.metadata(&module).tags, ["kick"]);
+
+        write_module(&project, "a", "// @tags snare\n", 2);
+        modules.rescan();
+        assert_eq!(modules.metadata(&module).tags, ["snare"]);
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn opening_a_project_does_not_seed_templates() {
+        let project = project("no-seed");
+        let mut modules = Modules::new(project.clone());
+        assert!(!project.join("templates").exists());
+        assert_eq!(modules.template.as_deref(), Some("main"));
+
+        modules.seed_templates();
+        assert!(project.join("templates/main").exists());
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn replace_all_applies_only_the_preview() {
+        let project = project("replace");
+        write_module(&project, "a", "let gain = 1;\n", 1);
+        write_module(&project, "b", "let gain = 2;\n", 1);
+        let mut modules = Modules::new(project.clone());
+        let (a, b) = (ModuleId::new("a").unwrap(), ModuleId::new("b").unwrap());
+        modules.find_text = "gain".into();
+        modules.replace_text = "level".into();
+        modules.preview_replace();
+
+        // changed after the preview without going through an editor
+        modules.modules.insert(b.clone(), "let gain = 3;\n".into());
+        modules.replace_text = "volume".into();
+        modules.replace_all();
+        assert_eq!(modules.modules[&a], "let level = 1;\n");
+        assert_eq!(modules.modules[&b], "let gain = 3;\n");
+        assert_eq!(modules.file_errors.len(), 1);
+        assert!(modules.replace_preview.is_none());
+        let _ = fs::remove_dir_all(&project);
+    }
+
+    #[test]
+    fn rescan_drops_the_replace_preview() {
+        let project = project("replace-rescan");
+        write_module(&project, "a", "let gain = 1;\n", 1);
+        let mut modules = Modules::new(project.clone());
+        modules.find_text = "gain".into();
+        modules.preview_replace();
+        assert!(modules.replace_preview.is_some());
+
+        write_module(&project, "a", "let gain = 2;\n", 2);
+        modules.rescan();
+        assert!(modules.replace_preview.is_none());
+        let _ = fs::remove_dir_all(&project);
+    }
+}
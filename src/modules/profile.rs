@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use super::render::{Render, Rendered};
+use super::{render_budget, RENDER_BUDGET, TEST_SCALE};
+use crate::completion;
+use crate::diagnostic::Diagnostic;
+
+/// Seconds of audio rendered by the PROFILE button.
+pub const PROFILE_SECONDS: f64 = 1.0;
+/// Calls timed for each function other than `main`.
+const FUNCTION_CALLS: usize = 1000;
+
+/// A measured time, or the budget a render ran over before it was stopped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timing {
+    Took(Duration),
+    Over(Duration),
+}
+
+impl Timing {
+    fn duration(self) -> Duration {
+        match self {
+            Timing::Took(d) | Timing::Over(d) => d,
+        }
+    }
+
+    fn per_call(self, calls: usize) -> Self {
+        match self {
+            Timing::Took(d) => Timing::Took(d / calls as u32),
+            Timing::Over(d) => Timing::Over(d / calls as u32),
+        }
+    }
+}
+
+/// How fast a module renders, measured on the same inputs TEST plays.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub samples: usize,
+    pub elapsed: Timing,
+    /// sample rate the module has to keep up with
+    pub rate: usize,
+    /// time of one call to each function of the module taking a single argument
+    pub functions: Vec<(String, Timing)>,
+}
+
+impl Profile {
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.elapsed.duration().as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// How many times faster than playback the module renders, below 1 it can't run live.
+    /// For a render that was stopped this is the most it could be.
+    pub fn realtime_factor(&self) -> f64 {
+        self.samples_per_second() / self.rate as f64
+    }
+
+    /// One line result, like "12.3x real time, 590k samples/s at 48000 Hz".
+    pub fn summary(&self) -> String {
+        match self.elapsed {
+            Timing::Took(_) => format!(
+                "{:.1}x real time, {:.0}k samples/s at {} Hz",
+                self.realtime_factor(),
+                self.samples_per_second() / 1000.0,
+                self.rate
+            ),
+            Timing::Over(budget) => format!(
+                "slower than {:.2}x real time at {} Hz, stopped after {:.0}s",
+                self.realtime_factor(),
+                self.rate,
+                budget.as_secs_f64()
+            ),
+        }
+    }
+
+    /// Summary followed by the time per call of each function.
+    pub fn report(&self) -> String {
+        let mut report = self.summary();
+        for (name, timing) in &self.functions {
+            report += &match timing {
+                Timing::Took(time) => {
+                    format!("\n{name}: {:.2}µs per call", time.as_secs_f64() * 1e6)
+                }
+                Timing::Over(time) => {
+                    format!(
+                        "\n{name}: more than {:.0}µs per call",
+                        time.as_secs_f64() * 1e6
+                    )
+                }
+            };
+        }
+        report
+    }
+}
+
+/// A profile running in render processes: `main` over the seconds asked for, then each
+/// function other than `main` taking a single argument, one at a time.
+pub struct Profiling {
+    source: String,
+    samples: usize,
+    rate: usize,
+    elapsed: Option<Timing>,
+    functions: Vec<(String, Timing)>,
+    /// functions still to time, the last one first
+    pending: Vec<String>,
+    /// the function rendering, `None` for `main`
+    function: Option<String>,
+    render: Render,
+    budget: Duration,
+}
+
+impl Profiling {
+    /// Starts rendering `seconds` of `source` at `rate`. A render taking more than
+    /// [`super::SLOWEST`] times as long as playback is stopped and reported as too slow.
+    pub fn start(source: &str, seconds: f64, rate: usize) -> Result<Self, Diagnostic> {
+        let samples = (seconds * rate as f64) as usize;
+        let budget = render_budget(seconds);
+        let render = Render::start(source, "main", 0..samples, TEST_SCALE, budget)?;
+        let mut pending = completion::signatures(source)
+            .into_iter()
+            .filter(|s| s.name != "main" && s.params.len() == 1)
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+        pending.reverse();
+
+        Ok(Self {
+            source: source.to_string(),
+            samples,
+            rate,
+            elapsed: None,
+            functions: Vec::new(),
+            pending,
+            function: None,
+            render,
+            budget,
+        })
+    }
+
+    /// The profile once every render finished, `None` while one runs.
+    pub fn poll(&mut self) -> Option<Result<Profile, Diagnostic>> {
+        let result = self.render.poll()?;
+        self.next(result)
+    }
+
+    /// Blocks until every render finished.
+    pub fn wait(mut self) -> Result<Profile, Diagnostic> {
+        loop {
+            let result = self.render.wait();
+            if let Some(profile) = self.next(result) {
+                return profile;
+            }
+        }
+    }
+
+    /// Keeps the result of the render that finished and starts the next one.
+    fn next(
+        &mut self,
+        result: Result<Rendered, Diagnostic>,
+    ) -> Option<Result<Profile, Diagnostic>> {
+        let timing = match result {
+            Ok(rendered) => Some(Timing::Took(rendered.elapsed)),
+            Err(_) if self.render.is_aborted() => Some(Timing::Over(self.budget)),
+            Err(diagnostic) if self.function.is_none() => return Some(Err(diagnostic)),
+            // a function failing on the inputs of the profile is left out
+            Err(_) => None,
+        };
+        match (self.function.take(), timing) {
+            (None, timing) => self.elapsed = timing,
+            (Some(name), Some(timing)) => {
+                self.functions.push((name, timing.per_call(FUNCTION_CALLS)))
+            }
+            (Some(_), None) => (),
+        }
+
+        // the executor can only be timed from the outside, one function at a time
+        let Some(name) = self.pending.pop() else {
+            return Some(Ok(Profile {
+                samples: self.samples,
+                elapsed: self.elapsed?,
+                rate: self.rate,
+                functions: std::mem::take(&mut self.functions),
+            }));
+        };
+        self.budget = RENDER_BUDGET;
+        match Render::start(
+            &self.source,
+            &name,
+            0..FUNCTION_CALLS,
+            TEST_SCALE,
+            self.budget,
+        ) {
+            Ok(render) => self.render = render,
+            Err(diagnostic) => return Some(Err(diagnostic)),
+        }
+        self.function = Some(name);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(elapsed: Timing) -> Profile {
+        Profile {
+            samples: 48000,
+            elapsed,
+            rate: 48000,
+            functions: vec![
+                ("osc".into(), Timing::Took(Duration::from_micros(3))),
+                ("hang".into(), Timing::Over(Duration::from_millis(5))),
+            ],
+        }
+    }
+
+    #[test]
+    fn report_lists_each_function() {
+        let profile = profile(Timing::Took(Duration::from_millis(100)));
+        assert!((profile.realtime_factor() - 10.0).abs() < 1e-9);
+        assert_eq!(
+            profile.report(),
+            "10.0x real time, 480k samples/s at 48000 Hz\n\
+             osc: 3.00µs per call\n\
+             hang: more than 5000µs per call"
+        );
+    }
+
+    #[test]
+    fn stopped_render_is_a_bound_not_an_error() {
+        let profile = profile(Timing::Over(Duration::from_secs(15)));
+        assert!(profile.realtime_factor() < 1.0);
+        assert!(
+            profile
+                .summary()
+                .starts_with("slower than 0.07x real time at 48000 Hz"),
+            "{}",
+            profile.summary()
+        );
+    }
+}
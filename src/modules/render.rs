@@ -0,0 +1,295 @@
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::compile;
+use crate::diagnostic::{Diagnostic, Span};
+
+/// Samples of a finished render, with the time the executor took for them. Starting the
+/// process and compiling is not included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rendered {
+    pub samples: Vec<f32>,
+    pub elapsed: Duration,
+}
+
+/// Stdout and stderr of the render process.
+type Output = io::Result<(Vec<u8>, Vec<u8>)>;
+
+/// A render running in a `mksnd render` child process, killed once it runs over its
+/// budget or is dropped. The executor can't be interrupted inside a call, so only a
+/// process can stop a module that never returns.
+pub struct Render {
+    child: Child,
+    /// output of the process, read on a thread that ends with the process
+    output: Receiver<Output>,
+    started: Instant,
+    budget: Duration,
+    aborted: bool,
+}
+
+impl Render {
+    /// Starts rendering `function` of `source` over the inputs `range * scale`.
+    pub fn start(
+        source: &str,
+        function: &str,
+        range: Range<usize>,
+        scale: f64,
+        budget: Duration,
+    ) -> Result<Self, Diagnostic> {
+        let error = |e: io::Error| Diagnostic::new(format!("render: {e}"));
+        let mut child = Command::new(std::env::current_exe().map_err(error)?)
+            .arg("render")
+            .arg(function)
+            .arg(range.start.to_string())
+            .arg(range.end.to_string())
+            .arg(scale.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(error)?;
+
+        let (Some(mut stdin), Some(mut stdout), Some(mut stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            let _ = child.kill();
+            return Err(Diagnostic::new("render: missing pipes"));
+        };
+        let source = source.to_string();
+        let (sender, output) = mpsc::channel();
+        // stderr is read on its own thread, a child filling one pipe while the other
+        // is read would block until it is killed
+        let errors = thread::spawn(move || {
+            let mut err = Vec::new();
+            stderr.read_to_end(&mut err).map(|_| err)
+        });
+        thread::spawn(move || {
+            // a process that failed early doesn't read its input, its stderr tells why
+            let _ = stdin.write_all(source.as_bytes());
+            drop(stdin);
+            let mut out = Vec::new();
+            let read = stdout.read_to_end(&mut out).and_then(|_| {
+                let err = errors.join().unwrap_or_else(|_| Ok(Vec::new()))?;
+                Ok((out, err))
+            });
+            let _ = sender.send(read);
+        });
+
+        Ok(Self {
+            child,
+            output,
+            started: Instant::now(),
+            budget,
+            aborted: false,
+        })
+    }
+
+    /// The result once the process finished, `None` while it runs. Kills the process
+    /// once it ran over the budget.
+    pub fn poll(&mut self) -> Option<Result<Rendered, Diagnostic>> {
+        match self.output.try_recv() {
+            Ok(output) => Some(self.finish(output)),
+            Err(TryRecvError::Empty) if self.started.elapsed() < self.budget => None,
+            Err(TryRecvError::Empty) => Some(Err(self.abort())),
+            Err(TryRecvError::Disconnected) => Some(Err(Diagnostic::new("render crashed"))),
+        }
+    }
+
+    /// Blocks until the render finished or ran over the budget.
+    pub fn wait(&mut self) -> Result<Rendered, Diagnostic> {
+        let left = self.budget.saturating_sub(self.started.elapsed());
+        match self.output.recv_timeout(left) {
+            Ok(output) => self.finish(output),
+            Err(RecvTimeoutError::Timeout) => Err(self.abort()),
+            Err(RecvTimeoutError::Disconnected) => Err(Diagnostic::new("render crashed")),
+        }
+    }
+
+    fn finish(&mut self, output: Output) -> Result<Rendered, Diagnostic> {
+        let error = |e: io::Error| Diagnostic::new(format!("render: {e}"));
+        let (stdout, stderr) = output.map_err(error)?;
+        match self.child.wait().map_err(error)?.success() {
+            true => decode(&stdout).ok_or_else(|| Diagnostic::new("render: broken output")),
+            false => Err(decode_error(&String::from_utf8_lossy(&stderr))),
+        }
+    }
+
+    /// Whether the render was killed for running over its budget.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    fn abort(&mut self) -> Diagnostic {
+        self.aborted = true;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        Diagnostic::new(format!(
+            "render aborted after {:.0}s, check for loops that never end",
+            self.budget.as_secs_f64()
+        ))
+    }
+}
+
+impl Drop for Render {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Compiles `source` and calls `function` with the inputs `range * scale`, in this
+/// process. This is what `mksnd render` runs.
+pub fn run(
+    source: &str,
+    function: &str,
+    range: Range<usize>,
+    scale: f64,
+) -> Result<Rendered, Diagnostic> {
+    let executor = compile(source)?;
+    let start = Instant::now();
+    let samples = render_points(&executor, source, function, range, scale)?;
+    Ok(Rendered {
+        samples,
+        elapsed: start.elapsed(),
+    })
+}
+
+fn render_points(
+    e: &bs::executor::Executor,
+    source: &str,
+    function: &str,
+    range: Range<usize>,
+    scale: f64,
+) -> Result<Vec<f32>, Diagnostic> {
+    let mut points = Vec::new();
+    for i in range {
+        let input = i as f64 * scale;
+        let value = match e.execute(function, vec![&input]) {
+            Ok(value) => value,
+            Err(e) => {
+                let mut diagnostic = Diagnostic::from_error(source, "runtime error", &e);
+                diagnostic.message += &format!("\nat sample {i} (input = {input})");
+                return Err(diagnostic);
+            }
+        };
+        match value {
+            Some(bs::data::Value::Data(bs::data::DataType::Float(f))) => points.push(f as f32),
+            Some(v) => {
+                return Err(Diagnostic::new(format!(
+                    "{function} returned {v:?} at sample {i} (input = {input}), expected Num"
+                )))
+            }
+            None => {
+                return Err(Diagnostic::new(format!(
+                    "{function} returned nothing at sample {i} (input = {input}), expected Num"
+                )))
+            }
+        }
+    }
+
+    Ok(points)
+}
+
+/// Stdout of the render process: the elapsed nanoseconds as a little endian `u64`
+/// followed by the samples as little endian `f32`s.
+pub fn encode(rendered: &Rendered) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + rendered.samples.len() * 4);
+    bytes.extend((rendered.elapsed.as_nanos() as u64).to_le_bytes());
+    for sample in &rendered.samples {
+        bytes.extend(sample.to_le_bytes());
+    }
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Option<Rendered> {
+    let (elapsed, samples) = bytes.split_first_chunk::<8>()?;
+    if samples.len() % 4 != 0 {
+        return None;
+    }
+    Some(Rendered {
+        elapsed: Duration::from_nanos(u64::from_le_bytes(*elapsed)),
+        samples: samples
+            .chunks_exact(4)
+            .map(|s| f32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+            .collect(),
+    })
+}
+
+/// Stderr of a failed render process: the span as `line start end`, or `-` without
+/// one, and the message on the following lines.
+pub fn encode_error(diagnostic: &Diagnostic) -> String {
+    let span = match diagnostic.span {
+        Some(Span { line, start, end }) => format!("{line} {start} {end}"),
+        None => "-".into(),
+    };
+    format!("{span}\n{}", diagnostic.message)
+}
+
+fn decode_error(stderr: &str) -> Diagnostic {
+    let (span, message) = stderr.split_once('\n').unwrap_or(("-", stderr));
+    let numbers = span
+        .split(' ')
+        .map(str::parse)
+        .collect::<Result<Vec<usize>, _>>();
+    match numbers.as_deref() {
+        Ok(&[line, start, end]) => Diagnostic {
+            message: message.trim_end().to_string(),
+            span: Some(Span { line, start, end }),
+        },
+        // anything else, like a panic, is shown as it is
+        _ if span == "-" => Diagnostic::new(message.trim_end()),
+        _ => Diagnostic::new(stderr.trim_end()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_survive_the_pipe() {
+        let rendered = Rendered {
+            samples: vec![0.0, -1.5, f32::MAX, 0.25],
+            elapsed: Duration::from_micros(1234),
+        };
+        assert_eq!(decode(&encode(&rendered)), Some(rendered));
+        assert_eq!(decode(&[0; 7]), None);
+        assert_eq!(decode(&[0; 10]), None);
+    }
+
+    #[test]
+    fn diagnostics_survive_the_pipe() {
+        let diagnostic = Diagnostic {
+            message: "parse error\nexpected )".into(),
+            span: Some(Span {
+                line: 3,
+                start: 4,
+                end: 9,
+            }),
+        };
+        assert_eq!(decode_error(&encode_error(&diagnostic)), diagnostic);
+        let diagnostic = Diagnostic::new("main returned nothing");
+        assert_eq!(decode_error(&encode_error(&diagnostic)), diagnostic);
+    }
+
+    #[test]
+    fn run_calls_the_function_for_every_input() {
+        let source = "fn main(input: Num) -> Num {\n  return sin(input);\n}\n";
+        let rendered = run(source, "main", 1..4, 0.5).unwrap();
+        let expected = [0.5f32.sin(), 1.0f32.sin(), 1.5f32.sin()];
+        assert_eq!(rendered.samples.len(), expected.len());
+        for (sample, expected) in rendered.samples.iter().zip(expected) {
+            assert!((sample - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn other_errors_are_kept_whole() {
+        let panic = "thread 'main' panicked at src/lib.rs:1:1:\noverflow\n";
+        assert_eq!(decode_error(panic), Diagnostic::new(panic.trim_end()));
+    }
+}
@@ -0,0 +1,82 @@
+/// Scores how well `pattern` matches `text` if all of its characters appear in order,
+/// ignoring case. Consecutive characters and characters at the start of a word score
+/// higher, and the best scoring way to match the characters is used.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let pattern = pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    let text = text
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let word_start = |i: usize| i == 0 || !text[i - 1].is_alphanumeric();
+    let points = |i: usize, consecutive: bool| {
+        1 + if consecutive { 2 } else { 0 } + if word_start(i) { 3 } else { 0 }
+    };
+
+    // best score of the pattern so far with its last character matched at each index
+    let mut best = text
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (*c == pattern[0]).then(|| points(i, i == 0)))
+        .collect::<Vec<_>>();
+    for p in &pattern[1..] {
+        let mut next = vec![None; text.len()];
+        // best score ending at least two characters before the current one
+        let mut skipped = None::<u32>;
+        for i in 1..text.len() {
+            if text[i] == *p {
+                let after_skip = skipped.map(|s| s + points(i, false));
+                let after_previous = best[i - 1].map(|s| s + points(i, true));
+                next[i] = after_skip.max(after_previous);
+            }
+            skipped = skipped.max(best[i - 1]);
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Whether `content` contains `pattern`, ignoring case.
+pub fn contains(content: &str, pattern: &str) -> bool {
+    content.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_have_to_appear_in_order() {
+        assert!(fuzzy_score("kck", "drums/kick").is_some());
+        assert_eq!(fuzzy_score("kcik", "drums/kick"), None);
+        assert_eq!(fuzzy_score("snare", "drums/kick"), None);
+        assert_eq!(fuzzy_score("", "kick"), Some(0));
+        assert_eq!(fuzzy_score("kick", ""), None);
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(fuzzy_score("KICK", "kick"), fuzzy_score("kick", "kick"));
+        assert_eq!(fuzzy_score("kick", "KICK"), fuzzy_score("kick", "kick"));
+    }
+
+    #[test]
+    fn consecutive_and_word_start_matches_score_higher() {
+        let score = |text| fuzzy_score("bas", text).unwrap();
+        assert!(score("bass") > score("bxaxs"));
+        assert!(score("sub bass") > score("subbass"));
+        assert!(score("a bass") > score("abass"));
+    }
+
+    #[test]
+    fn contains_ignores_case() {
+        assert!(contains("fn Main() {}", "main"));
+        assert!(contains("fn main() {}", "MAIN"));
+        assert!(!contains("fn main() {}", "mian"));
+    }
+}
@@ -0,0 +1,279 @@
+use std::time::Instant;
+
+use iced::widget::text_editor::Content;
+
+use super::profile::PROFILE_SECONDS;
+use super::render::Render;
+use super::{
+    expect, test_budget, test_length, undo, Metadata, ModuleId, Profile, Profiling, LIVE_DELAY,
+    PREVIEW_SAMPLES, PREVIEW_SCALE, RENDER_BUDGET, TEST_RATE, TEST_SCALE,
+};
+use crate::completion::{self, Signature};
+use crate::diagnostic::{self, Diagnostic};
+use crate::lint;
+
+/// What the render running for a tab is for.
+pub enum Job {
+    /// render of a compile, showing its source's result once it finishes. A live one
+    /// keeps the last preview if it fails. `play` is set by a TEST waiting for it.
+    Preview {
+        source: String,
+        live: bool,
+        play: bool,
+    },
+    /// test render of the source of the last compile, linted once it finishes and
+    /// played if TEST asked for it
+    Test { play: bool },
+}
+
+/// What a finished render asks of the editor.
+pub enum Finished {
+    /// COMPILE built and rendered the module
+    Compiled,
+    Play(Vec<f32>),
+}
+
+/// A module open in the editor, with the render of its last compile.
+pub struct Tab {
+    pub module: ModuleId,
+    pub content: Content,
+    /// source of the last compile, used by TEST
+    pub source: String,
+    pub preview: Result<Vec<f32>, Diagnostic>,
+    pub last_edit: Option<Instant>,
+    /// error of the last live compile, shown while the previous render stays on screen
+    pub live_error: Option<Diagnostic>,
+    /// functions of the last successful compile, offered as completions
+    pub signatures: Vec<Signature>,
+    /// audio problems found in the test render of the last compile
+    pub lints: Vec<String>,
+    /// result of the last PROFILE
+    pub profile: Option<Result<Profile, Diagnostic>>,
+    /// PROFILE running for the buffer, its renders run besides the compile ones
+    pub profiling: Option<Profiling>,
+    /// the one render running for the tab, replaced when another one starts
+    pub render: Option<(Job, Render)>,
+}
+
+impl Tab {
+    pub fn new(module: ModuleId, text: &str) -> Self {
+        Self {
+            module,
+            content: Content::with_text(text),
+            source: String::new(),
+            preview: Err(Diagnostic::new("")),
+            last_edit: None,
+            live_error: None,
+            signatures: Vec::new(),
+            lints: Vec::new(),
+            profile: None,
+            profiling: None,
+            render: None,
+        }
+    }
+
+    pub fn state(&self) -> undo::State {
+        undo::State {
+            text: self.content.text(),
+            cursor: self.content.cursor_position(),
+        }
+    }
+
+    /// Replaces the buffer, putting the cursor back where it was.
+    pub fn set_state(&mut self, state: &undo::State) {
+        self.content = Content::with_text(&state.text);
+        let (line, column) = state.cursor;
+        for action in diagnostic::move_to(&state.text, line, column) {
+            self.content.perform(action);
+        }
+        self.last_edit = Some(Instant::now());
+    }
+
+    /// Word left of the cursor with the items completing it.
+    pub fn completions(&self) -> (String, Vec<completion::Item>) {
+        let (line, column) = self.content.cursor_position();
+        let Some(line) = self.content.line(line) else {
+            return (String::new(), Vec::new());
+        };
+        let prefix = completion::prefix(&line, column);
+        (
+            prefix.to_string(),
+            completion::complete(prefix, &self.signatures),
+        )
+    }
+
+    /// Signature of the call the cursor is in, or of the function name under it.
+    pub fn hint(&self) -> Option<Signature> {
+        let (line, column) = self.content.cursor_position();
+        let line = self.content.line(line)?;
+        let name = completion::call(&line, column).unwrap_or(completion::prefix(&line, column));
+        completion::lookup(name, &self.signatures)
+    }
+
+    /// Starts compiling and rendering the buffer, replacing any render still running.
+    pub fn compile(&mut self) {
+        self.live_error = None;
+        self.lints.clear();
+        self.start_preview(false);
+    }
+
+    fn start_preview(&mut self, live: bool) {
+        let source = self.content.text();
+        // a TEST waiting for the render this one replaces still plays afterwards
+        let play = matches!(
+            self.render,
+            Some((
+                Job::Test { play: true } | Job::Preview { play: true, .. },
+                _
+            ))
+        );
+        self.render = None;
+        match Render::start(
+            &source,
+            "main",
+            0..PREVIEW_SAMPLES,
+            PREVIEW_SCALE,
+            RENDER_BUDGET,
+        ) {
+            Ok(render) => self.render = Some((Job::Preview { source, live, play }, render)),
+            Err(diagnostic) if live => {
+                self.live_error = Some(diagnostic);
+                if play {
+                    self.resume_test(true);
+                }
+            }
+            Err(diagnostic) => {
+                self.source = source;
+                self.preview = Err(diagnostic);
+            }
+        }
+    }
+
+    /// Starts the render TEST plays. While a compile or the test render for the lints
+    /// runs, the test is played once it finishes instead.
+    pub fn test(&mut self) -> Result<(), Diagnostic> {
+        if let Some((Job::Test { play } | Job::Preview { play, .. }, _)) = &mut self.render {
+            *play = true;
+            return Ok(());
+        }
+        if self.preview.is_err() {
+            return Ok(());
+        }
+        self.start_test(true)
+    }
+
+    fn start_test(&mut self, play: bool) -> Result<(), Diagnostic> {
+        let length = test_length(&self.source);
+        let budget = test_budget(length);
+        let render = Render::start(&self.source, "main", 0..length, TEST_SCALE, budget)?;
+        self.render = Some((Job::Test { play }, render));
+        Ok(())
+    }
+
+    /// Starts the test render once a compile finished, a failure shows with the lints.
+    fn resume_test(&mut self, play: bool) {
+        if self.preview.is_err() {
+            return;
+        }
+        if let Err(diagnostic) = self.start_test(play) {
+            self.lints = vec![format!("test render: {}", diagnostic.summary())];
+        }
+    }
+
+    /// Audio problems and failed `@expect` checks of the test render.
+    fn lint(&self, samples: &[f32]) -> Vec<String> {
+        let expectations = Metadata::parse(&self.source).expect;
+        let mut lints = lint::lint(samples, TEST_RATE);
+        lints.extend(expect::failures(&expectations, samples, TEST_RATE));
+        lints
+    }
+
+    /// Starts profiling the buffer at `rate`, replacing a profile still running.
+    pub fn profile(&mut self, rate: usize) {
+        self.profiling = None;
+        match Profiling::start(&self.content.text(), PROFILE_SECONDS, rate) {
+            Ok(profiling) => self.profiling = Some(profiling),
+            Err(diagnostic) => self.profile = Some(Err(diagnostic)),
+        }
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profiling.is_some()
+    }
+
+    /// Takes the result of PROFILE once all its renders finished.
+    pub fn poll_profile(&mut self) {
+        if let Some(result) = self.profiling.as_mut().and_then(Profiling::poll) {
+            self.profile = Some(result);
+            self.profiling = None;
+        }
+    }
+
+    /// Takes the result of the running render once it finished.
+    pub fn poll(&mut self) -> Option<Finished> {
+        let (_, render) = self.render.as_mut()?;
+        let result = render.poll()?;
+        let (job, _) = self.render.take()?;
+        match (job, result) {
+            (
+                Job::Preview {
+                    live: true, play, ..
+                },
+                Err(diagnostic),
+            ) => {
+                // the last preview stays, so does the source TEST plays
+                self.live_error = Some(diagnostic);
+                if play {
+                    self.resume_test(true);
+                }
+                None
+            }
+            (Job::Preview { source, live, play }, result) => {
+                if result.is_ok() {
+                    self.signatures = completion::signatures(&source);
+                    self.live_error = None;
+                }
+                if live {
+                    // checking a full render on every pause in typing is too slow
+                    self.lints.clear();
+                }
+                self.source = source;
+                self.preview = result.map(|r| r.samples);
+                if self.preview.is_err() {
+                    return None;
+                }
+                if live {
+                    if play {
+                        self.resume_test(true);
+                    }
+                    return None;
+                }
+                // the lints come from the test render, which runs after COMPILE as well
+                self.resume_test(play);
+                Some(Finished::Compiled)
+            }
+            (Job::Test { play }, Ok(rendered)) => {
+                self.lints = self.lint(&rendered.samples);
+                play.then_some(Finished::Play(rendered.samples))
+            }
+            (Job::Test { play: true }, Err(diagnostic)) => {
+                self.preview = Err(diagnostic);
+                None
+            }
+            (Job::Test { play: false }, Err(diagnostic)) => {
+                self.lints = vec![format!("test render: {}", diagnostic.summary())];
+                None
+            }
+        }
+    }
+
+    /// Starts a compile once editing paused for [`LIVE_DELAY`]. Its result replaces the
+    /// preview only if the new code compiles and renders.
+    pub fn live_compile(&mut self) {
+        let due = self.last_edit.is_some_and(|t| t.elapsed() >= LIVE_DELAY);
+        if due {
+            self.last_edit = None;
+            self.start_preview(true);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::ModuleId;
+
+/// Templates offered while a project has no templates folder, and written to the
+/// folder of new projects.
+const DEFAULTS: &[(&str, &str)] = &[
+    (
+        "main",
+        "// @description {{name}}
+fn main(input: Num) -> Num {
+  return 0.0;
+}
+",
+    ),
+    (
+        "sine",
+        "// @description {{name}}: sine oscillator
+// @tags synth
+fn main(input: Num) -> Num {
+  let pitch = 200.0;
+  return sin(input * pitch) * 0.5;
+}
+",
+    ),
+    (
+        "kick",
+        "// @description {{name}}: kick drum with a falling pitch
+// @tags drums, kick
+// @length 0.5
+fn main(input: Num) -> Num {
+  let decay = 1.0 / (1.0 + input * 20.0);
+  let pitch = 30.0 + 200.0 * decay;
+  return sin(input * pitch) * decay;
+}
+",
+    ),
+    (
+        "hat",
+        "// @description {{name}}: noisy hi-hat from detuned sines
+// @tags drums, hat
+// @length 0.25
+fn main(input: Num) -> Num {
+  let decay = 1.0 / (1.0 + input * 200.0);
+  let noise = sin(input * 7919.0) * sin(input * 10007.0 + sin(input * 3571.0) * 40.0);
+  return noise * decay * 0.5;
+}
+",
+    ),
+    (
+        "effect",
+        "// @description {{name}}: tremolo applied to the signal of source
+// @tags effect
+// replace source with the signal to process
+fn source(input: Num) -> Num {
+  return sin(input * 200.0);
+}
+
+fn main(input: Num) -> Num {
+  let depth = 0.5;
+  let tremolo = 1.0 - depth + depth * sin(input * 30.0);
+  return source(input) * tremolo;
+}
+",
+    ),
+];
+
+/// Creates the templates folder with the default templates, unless it already exists.
+pub fn seed(dir: &Path) -> io::Result<()> {
+    if dir.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)?;
+    for (name, template) in DEFAULTS {
+        fs::write(dir.join(name), template)?;
+    }
+    Ok(())
+}
+
+/// Names of the templates in `dir`, sorted, or of the defaults if there is no `dir`.
+pub fn list(dir: &Path) -> Vec<String> {
+    if !dir.exists() {
+        let mut defaults = DEFAULTS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<_>>();
+        defaults.sort_unstable();
+        return defaults;
+    }
+    let Ok(dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut templates = dir
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.'))
+        .collect::<Vec<_>>();
+    templates.sort_unstable();
+    templates
+}
+
+/// Reads a template and fills in its placeholders for `module`: `{{name}}` is the
+/// module's file name and `{{path}}` its path inside the modules folder.
+pub fn instantiate(dir: &Path, template: &str, module: &ModuleId) -> io::Result<String> {
+    let default = DEFAULTS.iter().find(|(name, _)| *name == template);
+    let template = match (dir.exists(), default) {
+        (false, Some((_, template))) => template.to_string(),
+        _ => fs::read_to_string(dir.join(template))?,
+    };
+    let name = module.as_path().file_name().unwrap_or_default();
+    Ok(template
+        .replace("{{name}}", &name.to_string_lossy())
+        .replace("{{path}}", &module.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::temp_folder;
+    use super::*;
+
+    #[test]
+    fn defaults_without_a_folder() {
+        let dir = temp_folder("no-templates");
+        assert_eq!(list(&dir), ["effect", "hat", "kick", "main", "sine"]);
+        let module = ModuleId::new("drums/bd").unwrap();
+        let kick = instantiate(&dir, "kick", &module).unwrap();
+        assert!(kick.starts_with("// @description bd: kick drum"));
+        assert!(instantiate(&dir, "missing", &module).is_err());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn folder_replaces_the_defaults() {
+        let dir = temp_folder("templates");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pad"), "// {{path}}\n").unwrap();
+        assert_eq!(list(&dir), ["pad"]);
+        let module = ModuleId::new("synth/pad").unwrap();
+        assert_eq!(instantiate(&dir, "pad", &module).unwrap(), "// synth/pad\n");
+        assert!(instantiate(&dir, "kick", &module).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn seed_writes_the_defaults_once() {
+        let dir = temp_folder("seed");
+        seed(&dir).unwrap();
+        assert_eq!(list(&dir), ["effect", "hat", "kick", "main", "sine"]);
+        fs::remove_file(dir.join("hat")).unwrap();
+        seed(&dir).unwrap();
+        assert!(!dir.join("hat").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,193 @@
+use std::time::{Duration, Instant};
+
+use iced::widget::text_editor::Edit;
+
+/// Edits of the same kind closer together than this are undone as one step.
+const GROUP_TIMEOUT: Duration = Duration::from_secs(1);
+/// Undo steps kept per module.
+const UNDO_LIMIT: usize = 500;
+
+/// Buffer of a module at one point of its history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub text: String,
+    /// line and byte column of the editor cursor
+    pub cursor: (usize, usize),
+}
+
+/// Kind of edit, a word typed or deleted in one go forms a single undo step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Group {
+    Typing,
+    Deleting,
+    Other,
+}
+
+impl Group {
+    fn of(edit: &Edit) -> Self {
+        match edit {
+            Edit::Insert(c) if !c.is_whitespace() => Group::Typing,
+            Edit::Backspace | Edit::Delete => Group::Deleting,
+            _ => Group::Other,
+        }
+    }
+}
+
+/// Undo and redo stacks of one module.
+#[derive(Clone, Debug, Default)]
+pub struct Undo {
+    undo: Vec<State>,
+    redo: Vec<State>,
+    /// group of the last edit and when it happened, edits joining it are not recorded
+    last: Option<(Group, Instant)>,
+}
+
+impl Undo {
+    /// Records the buffer before `edit` is applied to it, unless the edit continues the
+    /// current group.
+    pub fn edit(&mut self, edit: &Edit, before: impl FnOnce() -> State) {
+        let group = Group::of(edit);
+        let joins = group != Group::Other
+            && self
+                .last
+                .is_some_and(|(last, at)| last == group && at.elapsed() < GROUP_TIMEOUT);
+        if !joins {
+            self.push(before());
+        }
+        self.last = Some((group, Instant::now()));
+        self.redo.clear();
+    }
+
+    /// Records the buffer before a change that is always its own undo step.
+    pub fn push(&mut self, state: State) {
+        self.undo.push(state);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.last = None;
+    }
+
+    /// Ends the current group, so the next edit starts a new undo step.
+    pub fn break_group(&mut self) {
+        self.last = None;
+    }
+
+    /// State to go back to, `current` becomes redoable.
+    pub fn undo(&mut self, current: State) -> Option<State> {
+        let state = self.undo.pop()?;
+        self.redo.push(current);
+        self.last = None;
+        Some(state)
+    }
+
+    /// State to go forward to, `current` becomes undoable.
+    pub fn redo(&mut self, current: State) -> Option<State> {
+        let state = self.redo.pop()?;
+        self.undo.push(current);
+        self.last = None;
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(text: &str) -> State {
+        State {
+            text: text.into(),
+            cursor: (0, text.len()),
+        }
+    }
+
+    /// Applies `edits` to `text` like the editor would, recording them in `undo`.
+    fn type_edits(undo: &mut Undo, text: &mut String, edits: &[Edit]) {
+        for edit in edits {
+            undo.edit(edit, || state(text));
+            match edit {
+                Edit::Insert(c) => text.push(*c),
+                Edit::Backspace => {
+                    text.pop();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn a_word_is_one_step() {
+        let mut undo = Undo::default();
+        let mut text = String::new();
+        type_edits(
+            &mut undo,
+            &mut text,
+            &[Edit::Insert('l'), Edit::Insert('e')],
+        );
+        type_edits(
+            &mut undo,
+            &mut text,
+            &[Edit::Insert('t'), Edit::Insert(' ')],
+        );
+        type_edits(&mut undo, &mut text, &[Edit::Insert('x')]);
+
+        assert_eq!(undo.undo(state(&text)), Some(state("let ")));
+        assert_eq!(undo.undo(state("let ")), Some(state("let")));
+        assert_eq!(undo.undo(state("let")), Some(state("")));
+        assert_eq!(undo.undo(state("")), None);
+    }
+
+    #[test]
+    fn switching_between_typing_and_deleting_starts_a_step() {
+        let mut undo = Undo::default();
+        let mut text = String::new();
+        type_edits(
+            &mut undo,
+            &mut text,
+            &[Edit::Insert('a'), Edit::Insert('b')],
+        );
+        type_edits(&mut undo, &mut text, &[Edit::Backspace, Edit::Backspace]);
+
+        assert_eq!(text, "");
+        assert_eq!(undo.undo(state("")), Some(state("ab")));
+        assert_eq!(undo.undo(state("ab")), Some(state("")));
+    }
+
+    #[test]
+    fn break_group_splits_a_word() {
+        let mut undo = Undo::default();
+        let mut text = String::new();
+        type_edits(&mut undo, &mut text, &[Edit::Insert('a')]);
+        undo.break_group();
+        type_edits(&mut undo, &mut text, &[Edit::Insert('b')]);
+
+        assert_eq!(undo.undo(state("ab")), Some(state("a")));
+    }
+
+    #[test]
+    fn redo_is_cleared_by_an_edit() {
+        let mut undo = Undo::default();
+        let mut text = String::new();
+        type_edits(&mut undo, &mut text, &[Edit::Insert('a')]);
+        assert_eq!(undo.undo(state("a")), Some(state("")));
+        assert_eq!(undo.redo(state("")), Some(state("a")));
+        assert_eq!(undo.undo(state("a")), Some(state("")));
+
+        let mut text = String::new();
+        type_edits(&mut undo, &mut text, &[Edit::Insert('b')]);
+        assert_eq!(undo.redo(state("b")), None);
+    }
+
+    #[test]
+    fn keeps_at_most_the_limit() {
+        let mut undo = Undo::default();
+        for i in 0..UNDO_LIMIT + 10 {
+            undo.push(state(&i.to_string()));
+        }
+        let mut steps = 0;
+        while undo.undo(state("")).is_some() {
+            steps += 1;
+        }
+        assert_eq!(steps, UNDO_LIMIT);
+    }
+}
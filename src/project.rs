@@ -0,0 +1,66 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Amount of projects kept in the recent projects list.
+const RECENT_LIMIT: usize = 10;
+
+/// Folder holding the settings shared by all projects.
+pub fn config_path() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config) => PathBuf::from(config),
+        None => match env::var_os("APPDATA") {
+            Some(config) => PathBuf::from(config),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        },
+    };
+    Some(config.join("mksnd"))
+}
+
+/// Recently opened project folders, most recent first.
+pub fn recent() -> Vec<PathBuf> {
+    let Some(path) = config_path() else {
+        return Vec::new();
+    };
+    let Ok(recent) = fs::read_to_string(path.join("recent")) else {
+        return Vec::new();
+    };
+    recent
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Moves `project` to the front of the recent projects list.
+pub fn add_recent(project: &Path) -> io::Result<()> {
+    let Some(path) = config_path() else {
+        return Ok(());
+    };
+    let mut recent = recent();
+    recent.retain(|p| p != project);
+    recent.insert(0, project.to_path_buf());
+    recent.truncate(RECENT_LIMIT);
+
+    let recent = recent
+        .iter()
+        .map(|p| p.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::create_dir_all(&path)?;
+    fs::write(path.join("recent"), recent)
+}
+
+/// Resolves a project folder, creating it with an empty `modules` folder if `create` is set.
+pub fn open(path: &Path, create: bool) -> io::Result<PathBuf> {
+    if create {
+        fs::create_dir_all(path.join("modules"))?;
+    } else if !path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not a folder", path.display()),
+        ));
+    }
+    path.canonicalize()
+}
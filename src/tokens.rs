@@ -0,0 +1,116 @@
+use std::ops::Range;
+
+use bs::lexer::TokenKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Keyword,
+    Type,
+    Number,
+    Operator,
+    Function,
+    Identifier,
+    Punctuation,
+    Comment,
+}
+
+/// A token of BullScript source, classified by the kind the lexer gave it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub range: Range<usize>,
+    pub text: &'a str,
+    pub kind: Kind,
+}
+
+/// Splits `source` with [`bs::lexer::tokenize`] and keeps the lexer's token kinds, so
+/// highlighting and formatting always agree with the real lexer.
+pub fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut lexed = bs::lexer::tokenize(source)
+        .into_iter()
+        .filter(|t| t.range.start < t.range.end && source.get(t.range.clone()).is_some())
+        .collect::<Vec<_>>();
+    lexed.sort_unstable_by_key(|t| t.range.start);
+
+    let mut tokens: Vec<Token> = Vec::with_capacity(lexed.len());
+    for (i, token) in lexed.iter().enumerate() {
+        let text = &source[token.range.clone()];
+        let next = lexed.get(i + 1).map(|n| &source[n.range.clone()]);
+        tokens.push(Token {
+            range: token.range.clone(),
+            text,
+            kind: classify(&token.kind, text, next),
+        });
+    }
+    tokens
+}
+
+/// Whether the lexer reads `word` as a keyword.
+pub fn is_keyword(word: &str) -> bool {
+    matches!(tokenize(word).as_slice(), [token] if token.kind == Kind::Keyword)
+}
+
+fn classify(kind: &TokenKind, text: &str, next: Option<&str>) -> Kind {
+    match kind {
+        TokenKind::Keyword(_) => Kind::Keyword,
+        TokenKind::Comment(_) => Kind::Comment,
+        TokenKind::Number(_) => Kind::Number,
+        TokenKind::Ident(_) if next == Some("(") => Kind::Function,
+        TokenKind::Ident(_) if text.starts_with(char::is_uppercase) => Kind::Type,
+        TokenKind::Ident(_) => Kind::Identifier,
+        // the lexer has one kind for all symbols, operators only get their own color
+        _ if text.chars().all(|c| "+-*/%=<>!&|^".contains(c)) => Kind::Operator,
+        _ => Kind::Punctuation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(&str, Kind)> {
+        tokenize(source)
+            .into_iter()
+            .map(|t| (t.text, t.kind))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_by_lexer_kind() {
+        assert_eq!(
+            kinds("fn main(input: Num) -> Num"),
+            [
+                ("fn", Kind::Keyword),
+                ("main", Kind::Function),
+                ("(", Kind::Punctuation),
+                ("input", Kind::Identifier),
+                (":", Kind::Punctuation),
+                ("Num", Kind::Type),
+                (")", Kind::Punctuation),
+                ("->", Kind::Operator),
+                ("Num", Kind::Type),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_numbers() {
+        assert_eq!(
+            kinds("let a = 1.5; // note"),
+            [
+                ("let", Kind::Keyword),
+                ("a", Kind::Identifier),
+                ("=", Kind::Operator),
+                ("1.5", Kind::Number),
+                (";", Kind::Punctuation),
+                ("// note", Kind::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_come_from_the_lexer() {
+        assert!(is_keyword("return"));
+        assert!(!is_keyword("sin"));
+        assert!(!is_keyword("return x"));
+    }
+}
@@ -17,6 +17,8 @@ pub struct Menu<Message, Data: Clone + PartialEq> {
     elements: Vec<Element<Data>>,
     on_select: fn(Data) -> Message,
     selected: Option<Data>,
+    /// element highlighted by keyboard navigation
+    cursor: Option<usize>,
     flow: Flow,
     element_size: f32,
 }
@@ -27,6 +29,7 @@ impl<Message: Clone, Data: Clone + PartialEq> Menu<Message, Data> {
             elements,
             on_select,
             selected: None,
+            cursor: None,
             flow,
             element_size: 200.0,
         }
@@ -36,12 +39,43 @@ impl<Message: Clone, Data: Clone + PartialEq> Menu<Message, Data> {
         self.selected = Some(selection)
     }
 
+    pub fn deselect(&mut self) {
+        self.selected = None
+    }
+
     pub fn selected(&self) -> &Option<Data> {
         &self.selected
     }
 
     pub fn set_elements(&mut self, elements: Vec<Element<Data>>) {
         self.elements = elements;
+        self.cursor = match self.elements.len() {
+            0 => None,
+            len => self.cursor.map(|c| c.min(len - 1)),
+        };
+    }
+
+    /// Moves the keyboard cursor by `delta` elements, starting at the first or last one.
+    pub fn move_cursor(&mut self, delta: isize) {
+        let Some(last) = self.elements.len().checked_sub(1) else {
+            return;
+        };
+        self.cursor = Some(match self.cursor {
+            Some(c) => c.saturating_add_signed(delta).min(last),
+            None if delta < 0 => last,
+            None => 0,
+        });
+    }
+
+    /// Puts the keyboard cursor on the first element.
+    pub fn reset_cursor(&mut self) {
+        self.cursor = (!self.elements.is_empty()).then_some(0);
+    }
+
+    /// Message selecting the element under the keyboard cursor.
+    pub fn submit(&self) -> Option<Message> {
+        let element = self.elements.get(self.cursor?)?;
+        Some((self.on_select)(element.data.clone()))
     }
 }
 
@@ -52,7 +86,8 @@ impl<'a, Message: Clone + 'a, Data: Clone + PartialEq + 'a> From<Menu<Message, D
         let options = menu
             .elements
             .iter()
-            .map(|key| {
+            .enumerate()
+            .map(|(i, key)| {
                 let mut content = widget::button(widget::text(key.text.clone()))
                     // .on_press(Message::Editor(ModuleMessage::SelectModule(key.clone())))
                     .on_press((menu.on_select)(key.data.clone()));
@@ -60,6 +95,8 @@ impl<'a, Message: Clone + 'a, Data: Clone + PartialEq + 'a> From<Menu<Message, D
 
                 if Some(key.data.clone()) == menu.selected {
                     content = content.style(iced::theme::Button::Positive);
+                } else if Some(i) == menu.cursor {
+                    content = content.style(iced::theme::Button::Secondary);
                 }
                 content = content
                     .height(iced::Length::Shrink)
@@ -1,2 +1,4 @@
 pub mod menu;
+pub mod tree;
 pub use menu::Menu;
+pub use tree::Tree;
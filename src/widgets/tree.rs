@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use iced::{widget, Element as IcedElement};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element<Data: Clone + PartialEq> {
+    pub data: Data,
+    /// location of the leaf in the tree, folders are taken from its parents
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// Where the mouse was released.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target<Data> {
+    Leaf(Data),
+    Folder(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<Data> {
+    Grab(Data),
+    PressFolder,
+    Release(Target<Data>),
+}
+
+/// What the owner of the [`Tree`] should do after an [`Event`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action<Data> {
+    Select(Data),
+    Move(Data, PathBuf),
+}
+
+/// Collapsible folder tree. Clicking a leaf selects it, clicking a folder toggles it and
+/// dragging a leaf onto a folder moves it there.
+#[derive(Clone, Debug)]
+pub struct Tree<Message, Data: Clone + PartialEq> {
+    elements: Vec<Element<Data>>,
+    folders: Vec<PathBuf>,
+    expanded: HashSet<PathBuf>,
+    grabbed: Option<Data>,
+    on_event: fn(Event<Data>) -> Message,
+    root: String,
+    element_size: f32,
+    indent: f32,
+}
+
+impl<Message: Clone, Data: Clone + PartialEq> Tree<Message, Data> {
+    pub fn new(root: impl Into<String>, on_event: fn(Event<Data>) -> Message) -> Self {
+        Self {
+            elements: Vec::new(),
+            folders: Vec::new(),
+            expanded: HashSet::new(),
+            grabbed: None,
+            on_event,
+            root: root.into(),
+            element_size: 200.0,
+            indent: 12.0,
+        }
+    }
+
+    /// Sets the leaves and the folders, including empty ones, of the tree.
+    pub fn set_elements(&mut self, elements: Vec<Element<Data>>, folders: Vec<PathBuf>) {
+        self.elements = elements;
+        self.folders = folders;
+    }
+
+    /// Expands `folder` and all of its parents.
+    pub fn expand(&mut self, folder: &Path) {
+        for folder in folder.ancestors() {
+            self.expanded.insert(folder.to_path_buf());
+        }
+    }
+
+    pub fn update(&mut self, event: Event<Data>) -> Option<Action<Data>> {
+        match event {
+            Event::Grab(data) => {
+                self.grabbed = Some(data);
+                None
+            }
+            Event::PressFolder => {
+                self.grabbed = None;
+                None
+            }
+            Event::Release(target) => match (self.grabbed.take(), target) {
+                (Some(grabbed), Target::Leaf(data)) if grabbed == data => {
+                    Some(Action::Select(data))
+                }
+                (Some(grabbed), Target::Folder(folder)) => Some(Action::Move(grabbed, folder)),
+                (None, Target::Folder(folder)) => {
+                    if !self.expanded.remove(&folder) {
+                        self.expanded.insert(folder);
+                    }
+                    None
+                }
+                _ => None,
+            },
+        }
+    }
+
+    pub fn view<'a>(&self, selected: Option<&Data>) -> IcedElement<'a, Message>
+    where
+        Message: 'a,
+        Data: 'a,
+    {
+        let mut rows = vec![self.row(
+            0,
+            self.root.clone(),
+            false,
+            self.folder_events(Path::new("")),
+        )];
+        self.children(Path::new(""), 1, selected, &mut rows);
+
+        let content = widget::container(widget::column(rows))
+            .height(iced::Length::Shrink)
+            .width(iced::Length::Shrink);
+
+        widget::scrollable(content)
+            .direction(widget::scrollable::Direction::Both {
+                vertical: widget::scrollable::Properties::new(),
+                horizontal: widget::scrollable::Properties::new(),
+            })
+            .into()
+    }
+
+    fn children<'a>(
+        &self,
+        folder: &Path,
+        depth: usize,
+        selected: Option<&Data>,
+        rows: &mut Vec<IcedElement<'a, Message>>,
+    ) where
+        Message: 'a,
+        Data: 'a,
+    {
+        let mut folders = self
+            .folders
+            .iter()
+            .filter(|f| f.parent() == Some(folder))
+            .collect::<Vec<_>>();
+        folders.sort_unstable();
+
+        for child in folders {
+            let expanded = self.expanded.contains(child);
+            let name = child.file_name().unwrap_or_default().to_string_lossy();
+            let label = format!("{} {name}", if expanded { "-" } else { "+" });
+            rows.push(self.row(depth, label, false, self.folder_events(child)));
+            if expanded {
+                self.children(child, depth + 1, selected, rows);
+            }
+        }
+
+        let mut leaves = self
+            .elements
+            .iter()
+            .filter(|e| e.path.parent() == Some(folder))
+            .collect::<Vec<_>>();
+        leaves.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+        for leaf in leaves {
+            let highlighted =
+                Some(&leaf.data) == selected || Some(&leaf.data) == self.grabbed.as_ref();
+            let events = (
+                (self.on_event)(Event::Grab(leaf.data.clone())),
+                (self.on_event)(Event::Release(Target::Leaf(leaf.data.clone()))),
+            );
+            rows.push(self.row(depth, leaf.text.clone(), highlighted, events));
+        }
+    }
+
+    fn folder_events(&self, folder: &Path) -> (Message, Message) {
+        (
+            (self.on_event)(Event::PressFolder),
+            (self.on_event)(Event::Release(Target::Folder(folder.to_path_buf()))),
+        )
+    }
+
+    fn row<'a>(
+        &self,
+        depth: usize,
+        label: String,
+        highlighted: bool,
+        (press, release): (Message, Message),
+    ) -> IcedElement<'a, Message>
+    where
+        Message: 'a,
+    {
+        let content = widget::row([
+            widget::horizontal_space()
+                .width(iced::Length::Fixed(depth as f32 * self.indent))
+                .into(),
+            widget::text(label).into(),
+        ]);
+        let mut content = widget::container(content)
+            .padding(iced::Padding::from([2.0, 5.0]))
+            .width(iced::Length::Fixed(self.element_size));
+        if highlighted {
+            content = content.style(iced::theme::Container::Box);
+        }
+
+        widget::mouse_area(content)
+            .on_press(press)
+            .on_release(release)
+            .into()
+    }
+}